@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// profile.rs
+use crate::profile::RunProfile;
+// telemetry.rs
+use crate::telemetry::Telemetry;
+
+// Closer than this to the end of the stage counts as finished, the last packet
+// before the finish line may still be a few meters short of it.
+pub const FINISH_DISTANCE: f32 = 5.0; // meters
+// A run has to be followed from the start and without holes to count, one joined
+// halfway or with packets missing could be quicker than it really was.
+pub const START_DISTANCE: f32 = 20.0; // meters
+pub const MAX_PROGRESS_GAP: f32 = 100.0; // meters between kept frames
+
+// Best run file layout (bincode, little endian):
+//   BestHeader
+//   RunProfile
+pub const BEST_MAGIC: [u8; 4] = *b"RBRB";
+pub const BEST_VERSION: u16 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BestHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+}
+
+pub fn encode_best(run: &RunProfile) -> bincode::Result<Vec<u8>> {
+    let header = BestHeader {
+        magic: BEST_MAGIC,
+        version: BEST_VERSION,
+    };
+    let mut data = bincode::serialize(&header)?;
+    bincode::serialize_into(&mut data, run)?;
+    Ok(data)
+}
+
+pub fn decode_best(mut data: &[u8]) -> Result<RunProfile, String> {
+    let header: BestHeader = bincode::deserialize_from(&mut data)
+        .map_err(|e| format!("bad best run header: {e}"))?;
+    if header.magic != BEST_MAGIC {
+        return Err("not a best run file".to_string());
+    }
+    if header.version != BEST_VERSION {
+        return Err(format!("unsupported best run version {}", header.version));
+    }
+    bincode::deserialize(data).map_err(|e| e.to_string())
+}
+
+// The quickest finished run per stage and car, and the run in progress to compare
+// against it.
+#[derive(Default)]
+pub struct PersonalBests {
+    pub current: RunProfile,
+    bests: HashMap<(i32, i32), RunProfile>, // by stage and car index
+    finished: bool,
+    last_time: f32,
+}
+impl PersonalBests {
+    pub fn insert(&mut self, best: RunProfile) {
+        self.bests.insert((best.stage_index, best.car_index), best);
+    }
+
+    pub fn best(&self, stage: i32, car: i32) -> Option<&RunProfile> {
+        self.bests.get(&(stage, car))
+    }
+
+    pub fn len(&self) -> usize {
+        self.bests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bests.is_empty()
+    }
+
+    // Returns the run when it just finished quicker than the best, so it can be saved.
+    pub fn add(&mut self, telemetry: &Telemetry) -> Option<&RunProfile> {
+        let time = telemetry.stage.race_time;
+        let other_stage = !self.current.is_empty()
+            && (self.current.stage_index != telemetry.stage.index || self.current.car_index != telemetry.car.index);
+        // race time going backwards is a restart or a new stage
+        if time < self.last_time || other_stage {
+            self.current.clear();
+            self.finished = false;
+        }
+        self.last_time = time;
+        if self.finished || !self.current.add(telemetry) {
+            return None;
+        }
+        if telemetry.stage.distance_to_end > FINISH_DISTANCE {
+            return None;
+        }
+        self.finished = true;
+        if !self.is_complete() {
+            return None;
+        }
+        let key = (self.current.stage_index, self.current.car_index);
+        let faster = self.bests.get(&key).is_none_or(|best| self.current.end_time() < best.end_time());
+        if !faster {
+            return None;
+        }
+        self.bests.insert(key, self.current.clone());
+        self.bests.get(&key)
+    }
+
+    fn is_complete(&self) -> bool {
+        let distances = self.current.distances();
+        distances.first().is_some_and(|&d| d <= START_DISTANCE)
+            && distances.windows(2).all(|pair| pair[1] - pair[0] <= MAX_PROGRESS_GAP)
+    }
+
+    // Seconds behind the best run of this stage and car at the same distance,
+    // negative when ahead.
+    pub fn delta(&self, telemetry: &Telemetry) -> Option<f32> {
+        let stage = &telemetry.stage;
+        self.best(stage.index, telemetry.car.index)?.delta(stage.progress, stage.race_time)
+    }
+}
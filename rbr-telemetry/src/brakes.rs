@@ -0,0 +1,73 @@
+// telemetry.rs
+use crate::telemetry::Telemetry;
+
+// footbrake pressure in Pa above which a frame counts as braking
+pub const BRAKING_PRESSURE: f32 = 1.0e5;
+// °C of disk heating needed before the bias estimate is shown
+pub const MIN_BIAS_HEAT: f64 = 5.0;
+
+// Watches the brake disks over a stage: how long each disk has been above the
+// fade temperature, and the front/rear bias estimated from how much the disks
+// heat up while only the footbrake is applied. Order is LF, RF, LB, RB.
+#[derive(Default)]
+pub struct BrakeMonitor {
+    front_heat: f64,
+    rear_heat: f64,
+    last_temperatures: Option<[f32; 4]>,
+    hot_since: [Option<f32>; 4],
+    stage: i32,
+    last_race_time: f32,
+}
+impl BrakeMonitor {
+    pub fn add(&mut self, telemetry: &Telemetry, fade_temperature: f32) {
+        let race_time = telemetry.stage.race_time;
+        if telemetry.stage.index != self.stage || race_time < self.last_race_time {
+            *self = BrakeMonitor {
+                stage: telemetry.stage.index,
+                ..Default::default()
+            };
+        }
+        self.last_race_time = race_time;
+
+        let temperatures = disk_temperatures(telemetry);
+        let control = &telemetry.control;
+        // the handbrake only heats the rear disks
+        let footbrake_only = control.footbrake_pressure > BRAKING_PRESSURE && control.handbrake < 1.0;
+        if let (Some(last), true) = (self.last_temperatures, footbrake_only) {
+            let rise = |i: usize| (temperatures[i] - last[i]).max(0.0) as f64;
+            self.front_heat += rise(0) + rise(1);
+            self.rear_heat += rise(2) + rise(3);
+        }
+        self.last_temperatures = Some(temperatures);
+
+        for (since, temperature) in self.hot_since.iter_mut().zip(temperatures) {
+            if temperature > fade_temperature {
+                since.get_or_insert(race_time);
+            } else {
+                *since = None;
+            }
+        }
+    }
+
+    // Front share of the braking, 0..1. Cooling isn't accounted for, so it is
+    // only a rough estimate that settles after a few hard stops.
+    pub fn bias(&self) -> Option<f32> {
+        let total = self.front_heat + self.rear_heat;
+        (total >= MIN_BIAS_HEAT).then(|| (self.front_heat / total) as f32)
+    }
+
+    // Seconds of race time the disk has been above the fade temperature.
+    pub fn hot_for(&self, corner: usize) -> f32 {
+        self.hot_since[corner].map_or(0.0, |since| (self.last_race_time - since).max(0.0))
+    }
+}
+
+pub fn disk_temperatures(telemetry: &Telemetry) -> [f32; 4] {
+    let car = &telemetry.car;
+    [
+        car.suspension_lf.wheel.brake_disk.temperature,
+        car.suspension_rf.wheel.brake_disk.temperature,
+        car.suspension_lb.wheel.brake_disk.temperature,
+        car.suspension_rb.wheel.brake_disk.temperature,
+    ]
+}
@@ -0,0 +1,42 @@
+use std::collections::VecDeque;
+
+// telemetry.rs
+use crate::telemetry::Telemetry;
+
+// Engine and coolant temperatures over the last `window` seconds of race time,
+// to tell a slowly overheating engine from one that is just hot.
+pub struct EngineTrend {
+    pub window: f32,
+    samples: VecDeque<(f32, f32, f32)>, // race time, coolant, engine
+}
+impl EngineTrend {
+    pub fn new(window: f32) -> Self {
+        EngineTrend {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn add(&mut self, telemetry: &Telemetry) {
+        let time = telemetry.stage.race_time;
+        let engine = &telemetry.car.engine;
+        if self.samples.back().is_some_and(|&(t, _, _)| time < t) {
+            self.samples.clear();
+        }
+        // paused or before the start, nothing changes
+        if self.samples.back().is_some_and(|&(t, _, _)| time == t) {
+            return;
+        }
+        self.samples.push_back((time, engine.engine_coolant_temperature, engine.engine_temperature));
+        while self.samples.front().is_some_and(|&(t, _, _)| time - t > self.window) {
+            self.samples.pop_front();
+        }
+    }
+
+    // °C per minute for the coolant and the engine, once half the window is filled.
+    pub fn rate(&self) -> Option<(f32, f32)> {
+        let (&(t0, c0, e0), &(t1, c1, e1)) = (self.samples.front()?, self.samples.back()?);
+        let minutes = (t1 - t0) / 60.0;
+        (t1 - t0 >= self.window * 0.5 && minutes > 0.0).then(|| ((c1 - c0) / minutes, (e1 - e0) / minutes))
+    }
+}
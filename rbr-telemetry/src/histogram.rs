@@ -0,0 +1,161 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+// telemetry.rs
+use crate::telemetry::Car;
+
+pub const CORNER_NAMES: [&str; 4] = ["LF", "RF", "LB", "RB"];
+
+// Share of samples in each damper band, 0..1. Positive piston velocity is bump.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DamperBands {
+    pub low_bump: f32,
+    pub high_bump: f32,
+    pub low_rebound: f32,
+    pub high_rebound: f32,
+}
+
+// Piston velocity histogram for the four corners, either over the whole stage
+// or over a rolling window of race time.
+pub struct DamperHistogram {
+    pub bin_width: f32, // m/s
+    pub bin_count: usize, // half rebound, half bump
+    window: Option<f32>, // seconds, None keeps the whole stage
+    counts: [Vec<u32>; 4],
+    samples: VecDeque<(f32, [usize; 4])>,
+    total: u64,
+    last_time: Option<f32>,
+}
+impl DamperHistogram {
+    pub fn new(bin_width: f32, bin_count: usize) -> Self {
+        DamperHistogram {
+            bin_width,
+            bin_count,
+            window: None,
+            counts: std::array::from_fn(|_| vec![0; bin_count]),
+            samples: VecDeque::new(),
+            total: 0,
+            last_time: None,
+        }
+    }
+
+    pub fn window(&self) -> Option<f32> {
+        self.window
+    }
+
+    // Changing between stage and rolling mode starts over.
+    pub fn set_window(&mut self, window: Option<f32>) {
+        if window.is_some() != self.window.is_some() {
+            self.clear();
+        }
+        self.window = window;
+    }
+
+    pub fn clear(&mut self) {
+        for counts in &mut self.counts {
+            counts.fill(0);
+        }
+        self.samples.clear();
+        self.total = 0;
+        self.last_time = None;
+    }
+
+    pub fn add_car(&mut self, race_time: f32, car: &Car) {
+        self.add(race_time, [
+            car.suspension_lf.damper.piston_velocity,
+            car.suspension_rf.damper.piston_velocity,
+            car.suspension_lb.damper.piston_velocity,
+            car.suspension_rb.damper.piston_velocity,
+        ]);
+    }
+
+    // Frames without new race time, paused or repeated packets, would count the
+    // same piston velocity again and are skipped.
+    pub fn add(&mut self, time: f32, velocities: [f32; 4]) {
+        match self.last_time {
+            Some(last) if time == last => return,
+            // race time going backwards is a restart or a new stage
+            Some(last) if time < last => self.clear(),
+            _ => {},
+        }
+        self.last_time = Some(time);
+        let bins = velocities.map(|v| self.bin(v));
+        for (counts, bin) in self.counts.iter_mut().zip(bins) {
+            counts[bin] += 1;
+        }
+        self.total += 1;
+        if let Some(window) = self.window {
+            self.samples.push_back((time, bins));
+            while let Some(&(t, bins)) = self.samples.front() {
+                if time - t <= window {
+                    break;
+                }
+                for (counts, bin) in self.counts.iter_mut().zip(bins) {
+                    counts[bin] -= 1;
+                }
+                self.total -= 1;
+                self.samples.pop_front();
+            }
+        }
+    }
+
+    // Velocities past the outer bins are counted in them.
+    pub fn bin(&self, velocity: f32) -> usize {
+        if velocity.is_nan() {
+            return self.bin_count / 2;
+        }
+        let bin = (velocity / self.bin_width).floor() + (self.bin_count / 2) as f32;
+        bin.clamp(0.0, (self.bin_count - 1) as f32) as usize
+    }
+
+    pub fn bin_range(&self, bin: usize) -> (f32, f32) {
+        let min = (bin as f32 - (self.bin_count / 2) as f32) * self.bin_width;
+        (min, min + self.bin_width)
+    }
+
+    pub fn counts(&self, corner: usize) -> &[u32] {
+        &self.counts[corner]
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.total
+    }
+
+    // Bins are assigned by their center, so knees are effectively rounded to the bin width.
+    pub fn bands(&self, corner: usize, bump_knee: f32, rebound_knee: f32) -> DamperBands {
+        let mut bands = DamperBands::default();
+        if self.total == 0 {
+            return bands;
+        }
+        for (bin, &count) in self.counts[corner].iter().enumerate() {
+            let (min, max) = self.bin_range(bin);
+            let center = (min + max) * 0.5;
+            let share = count as f32 / self.total as f32;
+            match (center >= 0.0, center.abs() < if center >= 0.0 { bump_knee } else { rebound_knee }) {
+                (true, true) => bands.low_bump += share,
+                (true, false) => bands.high_bump += share,
+                (false, true) => bands.low_rebound += share,
+                (false, false) => bands.high_rebound += share,
+            }
+        }
+        bands
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("velocity_min,velocity_max");
+        for name in CORNER_NAMES {
+            csv.push(',');
+            csv.push_str(name);
+        }
+        csv.push('\n');
+        for bin in 0..self.bin_count {
+            let (min, max) = self.bin_range(bin);
+            let _ = write!(csv, "{min:.3},{max:.3}");
+            for counts in &self.counts {
+                let _ = write!(csv, ",{}", counts[bin]);
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
@@ -0,0 +1,266 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+
+// telemetry.rs
+use crate::telemetry::{Corner, Telemetry};
+// wire.rs
+use crate::wire::{Field, LAYOUTS};
+
+// A field of Telemetry that can be kept in a ChannelHistory, by its path such as
+// "car.suspension_lf.wheel.tire.pressure". The channels are every field the wire
+// layouts decode, so a field added to a layout is a channel without further work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct Channel(&'static str);
+impl Channel {
+    pub const THROTTLE: Channel = Channel("control.throttle");
+    pub const BRAKE: Channel = Channel("control.brake");
+    pub const HANDBRAKE: Channel = Channel("control.handbrake");
+    pub const CLUTCH: Channel = Channel("control.clutch");
+    pub const GEAR: Channel = Channel("control.gear");
+    pub const SPEED: Channel = Channel("car.speed");
+    pub const DISTANCE_TO_END: Channel = Channel("stage.distance_to_end");
+
+    pub const PEDALS: [Channel; 5] = [
+        Channel::THROTTLE,
+        Channel::BRAKE,
+        Channel::HANDBRAKE,
+        Channel::CLUTCH,
+        Channel::GEAR,
+    ];
+
+    pub fn all() -> Vec<Channel> {
+        fields().iter().map(|field| Channel(field.path)).collect()
+    }
+
+    pub fn find(path: &str) -> Option<Channel> {
+        field(path).map(|field| Channel(field.path))
+    }
+
+    // A field of one wheel, `path` is what follows the suspension, e.g. "wheel.tire.pressure".
+    pub fn corner(corner: Corner, path: &str) -> Option<Channel> {
+        Channel::find(&format!("car.{}.{path}", corner.path()))
+    }
+
+    pub fn path(&self) -> &'static str {
+        self.0
+    }
+
+    // The path in words, "Tire pressure LF" for "car.suspension_lf.wheel.tire.pressure".
+    pub fn name(&self) -> String {
+        let parts: Vec<&str> = self.0.split('.').collect();
+        let corner = Corner::ALL.into_iter().find(|c| parts.contains(&c.path()));
+        let words: Vec<&str> = parts.iter()
+            .enumerate()
+            // car and stage both have an index
+            .filter(|&(i, part)| match *part {
+                "car" | "stage" => parts.get(i + 1) == Some(&"index"),
+                "control" | "wheel" => false,
+                _ => corner.is_none_or(|c| *part != c.path()),
+            })
+            .map(|(_, part)| *part)
+            .collect();
+        let mut name = words.join(" ").replace('_', " ");
+        if let Some(first) = name.get(..1) {
+            name.replace_range(..1, &first.to_uppercase());
+        }
+        match corner {
+            Some(corner) => format!("{name} {}", corner.name()),
+            None => name,
+        }
+    }
+
+    // Units after Telemetry::format, temperatures are already in °C.
+    pub fn unit(&self) -> &'static str {
+        let path = self.0;
+        let last = path.rsplit('.').next().unwrap_or(path);
+        let rotation = matches!(last, "roll" | "pitch" | "yaw");
+        match path {
+            // format scales the pedals to percent
+            "control.throttle" | "control.brake" | "control.handbrake" | "control.clutch" => "%",
+            "car.speed" => "km/h",
+            "car.engine.rpm" => "rpm",
+            "car.roll" | "car.pitch" | "car.yaw" => "rad",
+            "stage.race_time" => "s",
+            _ if last.ends_with("temperature") => "°C",
+            _ if last.ends_with("pressure") => "Pa",
+            _ if last.ends_with("force") => "N",
+            _ if last == "wear" => "%",
+            _ if path.starts_with("car.velocities.") => if rotation { "rad/s" } else { "m/s" },
+            _ if path.starts_with("car.accelerations.") => if rotation { "rad/s²" } else { "m/s²" },
+            _ if path.contains("angular_velocity") => "rad/s",
+            _ if path.contains("angular_acceleration") => "rad/s²",
+            _ if path.contains("linear_velocity") || last == "piston_velocity" => "m/s",
+            _ if path.contains("linear_acceleration") => "m/s²",
+            _ if path.starts_with("car.position_")
+                || matches!(last, "progress" | "travel_distance" | "distance_to_end" | "spring_deflection") => "m",
+            _ => "",
+        }
+    }
+
+    pub fn value(&self, telemetry: &Telemetry) -> f32 {
+        field(self.0).map_or(f32::NAN, |field| (field.value)(telemetry))
+    }
+}
+// Stored as the path, read back as the channel of that path.
+impl<'de> Deserialize<'de> for Channel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = String::deserialize(deserializer)?;
+        Channel::find(&path).ok_or_else(|| serde::de::Error::custom(format!("unknown channel {path}")))
+    }
+}
+
+// Every field of every layout once, in layout order.
+fn fields() -> &'static [&'static Field] {
+    static FIELDS: OnceLock<Vec<&'static Field>> = OnceLock::new();
+    FIELDS.get_or_init(|| {
+        let mut fields: Vec<&'static Field> = Vec::new();
+        for field in LAYOUTS.into_iter().flat_map(|layout| layout.fields()) {
+            if !field.path.is_empty() && !fields.iter().any(|f| f.path == field.path) {
+                fields.push(field);
+            }
+        }
+        fields
+    })
+}
+
+fn field(path: &str) -> Option<&'static Field> {
+    static BY_PATH: OnceLock<HashMap<&'static str, &'static Field>> = OnceLock::new();
+    BY_PATH
+        .get_or_init(|| fields().iter().map(|&field| (field.path, field)).collect())
+        .get(path)
+        .copied()
+}
+
+// Every channel of the last `duration` seconds of race time, with the race time and
+// stage distance of each frame. Buffers grow with the frames they hold, so a short
+// duration doesn't cost the memory of a full `capacity`, and never past it.
+pub struct ChannelHistory {
+    capacity: usize,
+    duration: f32,
+    times: VecDeque<f32>,
+    distances: VecDeque<f32>,
+    channels: HashMap<Channel, VecDeque<f32>>,
+}
+impl ChannelHistory {
+    pub fn new(capacity: usize, duration: f32) -> Self {
+        ChannelHistory {
+            capacity,
+            duration,
+            times: VecDeque::new(),
+            distances: VecDeque::new(),
+            channels: Channel::all()
+                .into_iter()
+                .map(|channel| (channel, VecDeque::new()))
+                .collect(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn set_duration(&mut self, duration: f32) {
+        self.duration = duration;
+        self.trim();
+    }
+
+    pub fn clear(&mut self) {
+        self.times.clear();
+        self.distances.clear();
+        for values in self.channels.values_mut() {
+            values.clear();
+        }
+    }
+
+    pub fn add(&mut self, telemetry: &Telemetry) {
+        self.add_at(telemetry.stage.race_time, telemetry);
+    }
+
+    // Adds a frame against another clock than race time, e.g. when the game is paused.
+    pub fn add_at(&mut self, time: f32, telemetry: &Telemetry) {
+        // time going backwards is a restart or a new stage
+        if self.times.back().is_some_and(|&t| time < t) {
+            self.clear();
+        }
+        // paused or a repeated packet: the newest values replace the last frame, so a
+        // frozen race time can't pile up frames that the duration never trims
+        if self.times.back() == Some(&time) {
+            if let Some(distance) = self.distances.back_mut() {
+                *distance = telemetry.stage.progress;
+            }
+            for (channel, values) in &mut self.channels {
+                if let Some(value) = values.back_mut() {
+                    *value = channel.value(telemetry);
+                }
+            }
+            return;
+        }
+        if self.times.len() == self.capacity {
+            self.pop_front();
+        }
+        let capacity = self.capacity;
+        push(&mut self.times, time, capacity);
+        push(&mut self.distances, telemetry.stage.progress, capacity);
+        for (channel, values) in &mut self.channels {
+            push(values, channel.value(telemetry), capacity);
+        }
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        let Some(&last) = self.times.back() else {
+            return;
+        };
+        while self.times.front().is_some_and(|&t| last - t > self.duration) {
+            self.pop_front();
+        }
+    }
+
+    fn pop_front(&mut self) {
+        self.times.pop_front();
+        self.distances.pop_front();
+        for values in self.channels.values_mut() {
+            values.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    // Race time of each frame, oldest first.
+    pub fn times(&self) -> &VecDeque<f32> {
+        &self.times
+    }
+
+    // Stage distance of each frame in meters, oldest first.
+    pub fn distances(&self) -> &VecDeque<f32> {
+        &self.distances
+    }
+
+    pub fn values(&self, channel: Channel) -> &VecDeque<f32> {
+        static EMPTY: VecDeque<f32> = VecDeque::new();
+        self.channels.get(&channel).unwrap_or(&EMPTY)
+    }
+
+    pub fn latest(&self, channel: Channel) -> Option<f32> {
+        self.channels.get(&channel)?.back().copied()
+    }
+}
+
+// Doubles the buffer when it's full like push_back would, but only up to `capacity`.
+fn push(values: &mut VecDeque<f32>, value: f32, capacity: usize) {
+    if values.len() == values.capacity() {
+        values.reserve_exact(values.len().max(16).min(capacity - values.len()));
+    }
+    values.push_back(value);
+}
@@ -0,0 +1,42 @@
+// Telemetry packet layout, decoding, session files, history buffers and analysis for the NGP
+// Richard Burns Rally plugin, usable without the GUI.
+
+// telemetry.rs
+pub mod telemetry;
+pub use telemetry::*;
+
+// wire.rs
+pub mod wire;
+pub use wire::*;
+
+// session.rs
+pub mod session;
+pub use session::*;
+
+// history.rs
+pub mod history;
+pub use history::*;
+
+// histogram.rs
+pub mod histogram;
+pub use histogram::*;
+
+// pressure.rs
+pub mod pressure;
+pub use pressure::*;
+
+// brakes.rs
+pub mod brakes;
+pub use brakes::*;
+
+// engine.rs
+pub mod engine;
+pub use engine::*;
+
+// profile.rs
+pub mod profile;
+pub use profile::*;
+
+// best.rs
+pub mod best;
+pub use best::*;
@@ -0,0 +1,58 @@
+// telemetry.rs
+use crate::telemetry::Telemetry;
+
+// frames averaged for the cold pressures at the start of a stage
+pub const COLD_PRESSURE_FRAMES: u32 = 30;
+// Past both of these the tires are warming up, joining a stage later gives no cold pressures.
+pub const COLD_PRESSURE_TIME: f32 = 3.0; // seconds of race time
+pub const COLD_PRESSURE_DISTANCE: f32 = 30.0; // meters from the start
+
+// Tire pressures from the first frames of the stage, before the tires warm up,
+// to compare the running pressures against. Order is LF, RF, LB, RB.
+#[derive(Default)]
+pub struct StagePressures {
+    sum: [f64; 4],
+    frames: u32,
+    // the start is behind us, whatever was collected is all there will be
+    closed: bool,
+    stage: i32,
+    last_race_time: f32,
+}
+impl StagePressures {
+    pub fn add(&mut self, telemetry: &Telemetry) {
+        let race_time = telemetry.stage.race_time;
+        if telemetry.stage.index != self.stage || race_time < self.last_race_time {
+            *self = StagePressures {
+                stage: telemetry.stage.index,
+                ..Default::default()
+            };
+        }
+        self.last_race_time = race_time;
+        let at_start = race_time <= COLD_PRESSURE_TIME || telemetry.stage.progress <= COLD_PRESSURE_DISTANCE;
+        if !at_start {
+            self.closed = true;
+        }
+        if !self.closed && self.frames < COLD_PRESSURE_FRAMES {
+            for (sum, pressure) in self.sum.iter_mut().zip(tire_pressures(telemetry)) {
+                *sum += pressure as f64;
+            }
+            self.frames += 1;
+        }
+    }
+
+    // Pa, once enough frames were seen or the start has passed with fewer.
+    pub fn cold(&self) -> Option<[f32; 4]> {
+        let done = self.frames >= COLD_PRESSURE_FRAMES || (self.closed && self.frames > 0);
+        done.then(|| self.sum.map(|sum| (sum / self.frames as f64) as f32))
+    }
+}
+
+pub fn tire_pressures(telemetry: &Telemetry) -> [f32; 4] {
+    let car = &telemetry.car;
+    [
+        car.suspension_lf.wheel.tire.pressure,
+        car.suspension_rf.wheel.tire.pressure,
+        car.suspension_lb.wheel.tire.pressure,
+        car.suspension_rb.wheel.tire.pressure,
+    ]
+}
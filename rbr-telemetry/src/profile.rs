@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+// history.rs
+use crate::history::Channel;
+// session.rs
+use crate::session::Session;
+// telemetry.rs
+use crate::telemetry::Telemetry;
+
+// One run through a stage as race time and a few channels against stage distance,
+// so runs can be compared by where the car was rather than when.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunProfile {
+    pub stage_index: i32,
+    pub car_index: i32,
+    distances: Vec<f32>, // meters, always increasing
+    times: Vec<f32>, // race time in seconds
+    channels: Vec<(Channel, Vec<f32>)>,
+}
+impl RunProfile {
+    pub fn new(channels: &[Channel]) -> Self {
+        RunProfile {
+            channels: channels.iter().map(|&c| (c, Vec::new())).collect(),
+            ..Default::default()
+        }
+    }
+
+    // Every run in a recorded session. Race time going backwards or another stage or
+    // car starts a new run.
+    pub fn runs(session: &Session, channels: &[Channel]) -> Vec<RunProfile> {
+        let mut runs = Vec::new();
+        let mut run = RunProfile::new(channels);
+        let mut last: Option<(f32, i32, i32)> = None;
+        for frame in &session.frames {
+            let Ok(telemetry) = Telemetry::decode(&frame.data) else {
+                continue;
+            };
+            let (time, stage, car) = (telemetry.stage.race_time, telemetry.stage.index, telemetry.car.index);
+            if last.is_some_and(|(t, s, c)| time < t || stage != s || car != c) {
+                runs.push(std::mem::replace(&mut run, RunProfile::new(channels)));
+            }
+            last = Some((time, stage, car));
+            run.add(&telemetry);
+        }
+        runs.push(run);
+        runs.retain(|run| !run.is_empty());
+        runs
+    }
+
+    // The run on this (stage, car), or on any when None, that got furthest along the
+    // stage, or the quickest of those that got equally far.
+    pub fn from_session(session: &Session, channels: &[Channel], on: Option<(i32, i32)>) -> Option<RunProfile> {
+        RunProfile::runs(session, channels)
+            .into_iter()
+            .filter(|run| on.is_none_or(|on| (run.stage_index, run.car_index) == on))
+            .fold(None, better)
+    }
+
+    // Only frames that take the car further along the stage are kept, standing still
+    // or rolling back doesn't change where the time was lost. Returns whether it was kept.
+    pub fn add(&mut self, telemetry: &Telemetry) -> bool {
+        let distance = telemetry.stage.progress;
+        let time = telemetry.stage.race_time;
+        if time <= 0.0 || !distance.is_finite() || self.distances.last().is_some_and(|&d| distance <= d) {
+            return false;
+        }
+        if self.is_empty() {
+            self.stage_index = telemetry.stage.index;
+            self.car_index = telemetry.car.index;
+        }
+        self.distances.push(distance);
+        self.times.push(time);
+        for (channel, values) in &mut self.channels {
+            values.push(channel.value(telemetry));
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.distances.clear();
+        self.times.clear();
+        for (_, values) in &mut self.channels {
+            values.clear();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distances.is_empty()
+    }
+
+    pub fn distances(&self) -> &[f32] {
+        &self.distances
+    }
+
+    pub fn times(&self) -> &[f32] {
+        &self.times
+    }
+
+    pub fn values(&self, channel: Channel) -> Option<&[f32]> {
+        self.channels.iter().find(|(c, _)| *c == channel).map(|(_, v)| v.as_slice())
+    }
+
+    pub fn end_distance(&self) -> f32 {
+        self.distances.last().copied().unwrap_or(0.0)
+    }
+
+    pub fn end_time(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+
+    // Race time when this run passed `distance`, interpolated between frames.
+    pub fn time_at(&self, distance: f32) -> Option<f32> {
+        let (&first, &last) = (self.distances.first()?, self.distances.last()?);
+        if !(first..=last).contains(&distance) {
+            return None;
+        }
+        let i = self.distances.partition_point(|&d| d < distance);
+        if i == 0 {
+            return Some(self.times[0]);
+        }
+        let (d0, d1) = (self.distances[i - 1], self.distances[i]);
+        let (t0, t1) = (self.times[i - 1], self.times[i]);
+        Some(t0 + (t1 - t0) * (distance - d0) / (d1 - d0))
+    }
+
+    // Seconds behind this run at the same distance, negative when ahead.
+    pub fn delta(&self, distance: f32, race_time: f32) -> Option<f32> {
+        self.time_at(distance).map(|t| race_time - t)
+    }
+}
+
+fn better(best: Option<RunProfile>, run: RunProfile) -> Option<RunProfile> {
+    match best {
+        Some(best) if run.end_distance() < best.end_distance()
+            || (run.end_distance() == best.end_distance() && run.end_time() >= best.end_time()) => Some(best),
+        _ => Some(run),
+    }
+}
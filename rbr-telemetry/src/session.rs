@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Session file layout (bincode, little endian):
+//   SessionHeader
+//   SessionFrame, repeated until end of file
+// Frames hold the raw datagram so a session can be decoded again, or re-sent, later on.
+pub const SESSION_MAGIC: [u8; 4] = *b"RBRS";
+pub const SESSION_VERSION: u16 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub layout_version: u16, // wire::Layout::version
+    pub stage_index: i32,
+    pub car_index: i32,
+    pub started_at: u64, // unix seconds
+}
+impl SessionHeader {
+    pub fn new(stage_index: i32, car_index: i32, layout_version: u16) -> Self {
+        SessionHeader {
+            magic: SESSION_MAGIC,
+            version: SESSION_VERSION,
+            layout_version,
+            stage_index,
+            car_index,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionFrame {
+    pub time_us: u64, // since the start of the recording
+    pub data: Vec<u8>,
+}
+
+pub struct Session {
+    pub header: SessionHeader,
+    pub frames: Vec<SessionFrame>,
+}
+impl Session {
+    pub fn load(path: &Path) -> Result<Session, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(file);
+        let header: SessionHeader = bincode::deserialize_from(&mut reader)
+            .map_err(|e| format!("bad session header: {e}"))?;
+        if header.magic != SESSION_MAGIC {
+            return Err("not a session file".to_string());
+        }
+        if header.version != SESSION_VERSION {
+            return Err(format!("unsupported session version {}", header.version));
+        }
+        let mut frames = Vec::new();
+        while !reader.fill_buf().map_err(|e| e.to_string())?.is_empty() {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(frame) => frames.push(frame),
+                // a recording cut short by a crash still has its earlier frames
+                Err(_) => break,
+            }
+        }
+        Ok(Session { header, frames })
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.frames.last().map_or(0.0, |f| f.time_us as f64 / 1_000_000.0)
+    }
+
+    // Index of the last frame received at or before `time` seconds.
+    pub fn frame_at(&self, time: f64) -> usize {
+        let time_us = (time.max(0.0) * 1_000_000.0) as u64;
+        self.frames
+            .partition_point(|f| f.time_us <= time_us)
+            .saturating_sub(1)
+    }
+}
+
+pub fn write_header<W: Write>(writer: &mut W, header: &SessionHeader) -> bincode::Result<()> {
+    bincode::serialize_into(writer, header)
+}
+
+pub fn write_frame<W: Write>(writer: &mut W, frame: &SessionFrame) -> bincode::Result<()> {
+    bincode::serialize_into(writer, frame)
+}
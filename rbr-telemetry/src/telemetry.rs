@@ -0,0 +1,494 @@
+use std::fmt;
+
+// wire.rs
+use crate::wire::{layouts_with_size, Layout, TELEMETRY_DATA_LAYOUT};
+
+// Telemetry data is ported from the NGP rbr.telemetry.data.TelemetryData.h header
+// that is part of the NGP plugin, extended with the fields that only appear in
+// documentation/telemetry_documentation.txt. See wire.rs for how packets map onto it.
+
+#[derive(Default)]
+pub struct TireSegment {
+    pub temperature: f32,
+    pub wear: f32,
+}
+
+#[derive(Default)]
+pub struct Tire {
+    pub pressure: f32,
+    pub temperature: f32,
+    pub carcass_temperature: f32,
+    pub tread_temperature: f32,
+    pub current_segment: u32,
+    pub segment1: TireSegment,
+    pub segment2: TireSegment,
+    pub segment3: TireSegment,
+    pub segment4: TireSegment,
+    pub segment5: TireSegment,
+    pub segment6: TireSegment,
+    pub segment7: TireSegment,
+    pub segment8: TireSegment,
+}
+
+#[derive(Default)]
+pub struct BrakeDisk {
+    pub layer_temperature: f32,
+    pub temperature: f32,
+    pub wear: f32,
+}
+
+#[derive(Default)]
+pub struct Wheel {
+    pub brake_disk: BrakeDisk,
+    pub tire: Tire,
+}
+
+#[derive(Default)]
+pub struct Damper {
+    pub damage: f32,
+    pub piston_velocity: f32,
+}
+
+#[derive(Default)]
+pub struct Suspension {
+    pub spring_deflection: f32, 
+    pub rollbar_force: f32, // [N], -1.52779
+    pub spring_force: f32, // [N], 3684.53
+    pub damper_force: f32, // [N], 0.00844441
+    pub strut_force: f32, // [N], -3682.99
+    pub helper_spring_is_active: i32, // [""], bool?
+    pub damper: Damper,
+    pub wheel: Wheel,
+}
+
+#[derive(Default)]
+pub struct Engine {
+    pub rpm: f32,
+    pub radiator_coolant_temperature: f32,
+    pub engine_coolant_temperature: f32,
+    pub engine_temperature: f32,
+}
+
+#[derive(Default)]
+pub struct Motion {
+    pub surge: f32,
+    pub sway: f32,
+    pub heave: f32,
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+#[derive(Default)]
+pub struct Vector3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[derive(Default)]
+pub struct Car {
+    pub index: i32,
+    pub speed: f32,
+    pub position_x: f32,
+    pub position_y: f32,
+    pub position_z: f32,
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub velocities: Motion,
+    pub accelerations: Motion,
+    pub relative_linear_velocity: Vector3, // [m/s]
+    pub relative_angular_velocity: Vector3,
+    pub relative_linear_acceleration: Vector3, // [m/s/s]
+    pub relative_angular_acceleration: Vector3,
+    pub linear_velocity: Vector3, // [m/s]
+    pub angular_velocity: Vector3, // [rad/s]
+    pub linear_acceleration: Vector3, // [m/s/s]
+    pub angular_acceleration: Vector3, // [rad/s/s]
+    pub avg_linear_acceleration: Vector3, // [m/s/s]
+    pub avg_angular_acceleration: Vector3, // [rad/s/s]
+    pub engine: Engine,
+    pub suspension_lf: Suspension,
+    pub suspension_rf: Suspension,
+    pub suspension_lb: Suspension,
+    pub suspension_rb: Suspension,
+}
+
+impl Car {
+    // In LF, RF, LB, RB order.
+    pub fn suspensions(&self) -> [&Suspension; 4] {
+        [&self.suspension_lf, &self.suspension_rf, &self.suspension_lb, &self.suspension_rb]
+    }
+}
+
+// A wheel of the car. Per-corner arrays are in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Corner {
+    LF,
+    RF,
+    LB,
+    RB,
+}
+impl Corner {
+    pub const ALL: [Corner; 4] = [Corner::LF, Corner::RF, Corner::LB, Corner::RB];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    // The Car field of this wheel.
+    pub fn path(self) -> &'static str {
+        match self {
+            Corner::LF => "suspension_lf",
+            Corner::RF => "suspension_rf",
+            Corner::LB => "suspension_lb",
+            Corner::RB => "suspension_rb",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Corner::LF => "LF",
+            Corner::RF => "RF",
+            Corner::LB => "LB",
+            Corner::RB => "RB",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Control {
+    pub steering: f32,
+    pub throttle: f32,
+    pub brake: f32,
+    pub handbrake: f32,
+    pub clutch: f32,
+    pub gear: i32,
+    pub footbrake_pressure: f32,
+    pub handbrake_pressure: f32,
+}
+
+#[derive(Default)]
+pub struct Stage {
+    pub index: i32,
+    pub progress: f32, // meters
+    pub race_time: f32, // seconds
+    pub drive_line_location: f32, // no units?
+    pub travel_distance: f32, // meters
+    pub distance_to_end: f32, // meters
+}
+
+#[derive(Default)]
+pub struct Telemetry {
+    pub total_steps: u32, // meters
+    pub stage: Stage,
+    pub control: Control,
+    pub car: Car,
+}
+
+const KELVIN_TO_C: f32 = 273.15;
+
+// Size of the TelemetryData struct sent by the NGP plugin.
+pub const TELEMETRY_SIZE: usize = TELEMETRY_DATA_LAYOUT.size;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    WrongSize { expected: usize, actual: usize },
+    UnknownLayout { size: usize },
+    Layout(String),
+    NonFinite { field: &'static str },
+    OutOfRange { field: &'static str, value: f32 },
+}
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::WrongSize { expected, actual } => {
+                write!(f, "wrong packet size: expected {expected} bytes, got {actual}")
+            },
+            DecodeError::UnknownLayout { size } => write!(f, "no known layout is {size} bytes"),
+            DecodeError::Layout(e) => write!(f, "bad packet layout: {e}"),
+            DecodeError::NonFinite { field } => write!(f, "{field} is not a finite number"),
+            DecodeError::OutOfRange { field, value } => write!(f, "{field} out of range: {value}"),
+        }
+    }
+}
+
+// Counts of packets that failed to decode, by reason.
+#[derive(Default)]
+pub struct RejectedPackets {
+    pub wrong_size: u32,
+    pub unknown_layout: u32,
+    pub layout: u32,
+    pub non_finite: u32,
+    pub out_of_range: u32,
+    pub total: u32,
+    pub last_error: Option<DecodeError>,
+}
+impl RejectedPackets {
+    pub fn add(&mut self, error: &DecodeError) {
+        match error {
+            DecodeError::WrongSize { .. } => self.wrong_size += 1,
+            DecodeError::UnknownLayout { .. } => self.unknown_layout += 1,
+            DecodeError::Layout(_) => self.layout += 1,
+            DecodeError::NonFinite { .. } => self.non_finite += 1,
+            DecodeError::OutOfRange { .. } => self.out_of_range += 1,
+        }
+        self.total += 1;
+        self.last_error = Some(error.clone());
+    }
+}
+
+fn check_finite(field: &'static str, value: f32) -> Result<(), DecodeError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(DecodeError::NonFinite { field })
+    }
+}
+
+fn check_range(field: &'static str, value: f32, min: f32, max: f32) -> Result<(), DecodeError> {
+    check_finite(field, value)?;
+    if value < min || value > max {
+        return Err(DecodeError::OutOfRange { field, value });
+    }
+    Ok(())
+}
+
+impl Tire {
+    pub fn segments(&self) -> [&TireSegment; 8] {
+        [
+            &self.segment1, &self.segment2, &self.segment3, &self.segment4,
+            &self.segment5, &self.segment6, &self.segment7, &self.segment8,
+        ]
+    }
+
+    pub fn segments_mut(&mut self) -> [&mut TireSegment; 8] {
+        [
+            &mut self.segment1, &mut self.segment2, &mut self.segment3, &mut self.segment4,
+            &mut self.segment5, &mut self.segment6, &mut self.segment7, &mut self.segment8,
+        ]
+    }
+
+    fn convert_to_celsius(&mut self) {
+        self.temperature -= KELVIN_TO_C;
+        self.carcass_temperature -= KELVIN_TO_C;
+        self.tread_temperature -= KELVIN_TO_C;
+        for segment in self.segments_mut() {
+            segment.temperature -= KELVIN_TO_C;
+        }
+    }
+
+    fn validate(&self) -> Result<(), DecodeError> {
+        check_finite("tire.pressure", self.pressure)?;
+        check_finite("tire.temperature", self.temperature)?;
+        check_finite("tire.carcass_temperature", self.carcass_temperature)?;
+        check_finite("tire.tread_temperature", self.tread_temperature)?;
+        if self.current_segment > 7 {
+            return Err(DecodeError::OutOfRange {
+                field: "tire.current_segment",
+                value: self.current_segment as f32,
+            });
+        }
+        for segment in self.segments() {
+            check_finite("tire.segment.temperature", segment.temperature)?;
+            check_finite("tire.segment.wear", segment.wear)?;
+        }
+        Ok(())
+    }
+}
+
+impl Suspension {
+    fn validate(&self) -> Result<(), DecodeError> {
+        check_finite("suspension.spring_deflection", self.spring_deflection)?;
+        check_finite("suspension.rollbar_force", self.rollbar_force)?;
+        check_finite("suspension.spring_force", self.spring_force)?;
+        check_finite("suspension.damper_force", self.damper_force)?;
+        check_finite("suspension.strut_force", self.strut_force)?;
+        check_finite("damper.damage", self.damper.damage)?;
+        check_finite("damper.piston_velocity", self.damper.piston_velocity)?;
+        check_finite("brake_disk.layer_temperature", self.wheel.brake_disk.layer_temperature)?;
+        check_finite("brake_disk.temperature", self.wheel.brake_disk.temperature)?;
+        check_finite("brake_disk.wear", self.wheel.brake_disk.wear)?;
+        self.wheel.tire.validate()
+    }
+}
+
+impl Vector3 {
+    fn validate(&self, field: &'static str) -> Result<(), DecodeError> {
+        check_finite(field, self.x)?;
+        check_finite(field, self.y)?;
+        check_finite(field, self.z)
+    }
+}
+
+impl Motion {
+    fn validate(&self) -> Result<(), DecodeError> {
+        check_finite("motion.surge", self.surge)?;
+        check_finite("motion.sway", self.sway)?;
+        check_finite("motion.heave", self.heave)?;
+        check_finite("motion.roll", self.roll)?;
+        check_finite("motion.pitch", self.pitch)?;
+        check_finite("motion.yaw", self.yaw)
+    }
+}
+
+impl Telemetry {
+    // Decodes a raw datagram, rejecting anything that isn't a sane telemetry packet.
+    pub fn decode(data: &[u8]) -> Result<Telemetry, DecodeError> {
+        Telemetry::decode_layout(data).map(|(telemetry, _)| telemetry)
+    }
+
+    // Also returns the layout the packet was detected as. If several layouts share the
+    // packet size, the first one whose values pass validation wins.
+    pub fn decode_layout(data: &[u8]) -> Result<(Telemetry, &'static Layout), DecodeError> {
+        let mut error = DecodeError::UnknownLayout { size: data.len() };
+        for layout in layouts_with_size(data.len()) {
+            match Telemetry::decode_with(layout, data) {
+                Ok(telemetry) => return Ok((telemetry, layout)),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    pub fn decode_with(layout: &Layout, data: &[u8]) -> Result<Telemetry, DecodeError> {
+        let mut telemetry = layout.decode(data)?;
+        telemetry.validate()?;
+        telemetry.format();
+        Ok(telemetry)
+    }
+
+    // Checks the raw (unformatted) values.
+    pub fn validate(&self) -> Result<(), DecodeError> {
+        if self.stage.index < 0 {
+            return Err(DecodeError::OutOfRange {
+                field: "stage.index",
+                value: self.stage.index as f32,
+            });
+        }
+        check_finite("stage.progress", self.stage.progress)?;
+        check_range("stage.race_time", self.stage.race_time, 0.0, f32::MAX)?;
+        check_finite("stage.drive_line_location", self.stage.drive_line_location)?;
+        check_finite("stage.travel_distance", self.stage.travel_distance)?;
+        check_finite("stage.distance_to_end", self.stage.distance_to_end)?;
+
+        check_range("control.steering", self.control.steering, -1.0, 1.0)?;
+        check_range("control.throttle", self.control.throttle, 0.0, 1.0)?;
+        check_range("control.brake", self.control.brake, 0.0, 1.0)?;
+        check_range("control.handbrake", self.control.handbrake, 0.0, 1.0)?;
+        check_range("control.clutch", self.control.clutch, 0.0, 1.0)?;
+        // 0 is reverse, 1 is neutral
+        if !(0..=7).contains(&self.control.gear) {
+            return Err(DecodeError::OutOfRange {
+                field: "control.gear",
+                value: self.control.gear as f32,
+            });
+        }
+        check_finite("control.footbrake_pressure", self.control.footbrake_pressure)?;
+        check_finite("control.handbrake_pressure", self.control.handbrake_pressure)?;
+
+        let car = &self.car;
+        check_finite("car.speed", car.speed)?;
+        check_finite("car.position_x", car.position_x)?;
+        check_finite("car.position_y", car.position_y)?;
+        check_finite("car.position_z", car.position_z)?;
+        check_finite("car.roll", car.roll)?;
+        check_finite("car.pitch", car.pitch)?;
+        check_finite("car.yaw", car.yaw)?;
+        car.velocities.validate()?;
+        car.accelerations.validate()?;
+        car.relative_linear_velocity.validate("car.relative_linear_velocity")?;
+        car.relative_angular_velocity.validate("car.relative_angular_velocity")?;
+        car.relative_linear_acceleration.validate("car.relative_linear_acceleration")?;
+        car.relative_angular_acceleration.validate("car.relative_angular_acceleration")?;
+        car.linear_velocity.validate("car.linear_velocity")?;
+        car.angular_velocity.validate("car.angular_velocity")?;
+        car.linear_acceleration.validate("car.linear_acceleration")?;
+        car.angular_acceleration.validate("car.angular_acceleration")?;
+        car.avg_linear_acceleration.validate("car.avg_linear_acceleration")?;
+        car.avg_angular_acceleration.validate("car.avg_angular_acceleration")?;
+        check_finite("engine.rpm", car.engine.rpm)?;
+        check_finite("engine.radiator_coolant_temperature", car.engine.radiator_coolant_temperature)?;
+        check_finite("engine.engine_coolant_temperature", car.engine.engine_coolant_temperature)?;
+        check_finite("engine.engine_temperature", car.engine.engine_temperature)?;
+        car.suspension_lf.validate()?;
+        car.suspension_rf.validate()?;
+        car.suspension_lb.validate()?;
+        car.suspension_rb.validate()
+    }
+
+    pub fn format(&mut self) {
+        //self.stage.race_time += 0.7;
+        self.control.brake *= 100.0;
+        self.control.throttle *= 100.0;
+        self.control.clutch *= 100.0;
+        self.control.handbrake *= 100.0;
+        self.control.gear -= 1;
+        self.car.engine.radiator_coolant_temperature -= KELVIN_TO_C;
+        self.car.engine.engine_coolant_temperature -= KELVIN_TO_C;
+        self.car.engine.engine_temperature -= KELVIN_TO_C;
+        self.car.suspension_lf.wheel.brake_disk.temperature -= KELVIN_TO_C;
+        self.car.suspension_rf.wheel.brake_disk.temperature -= KELVIN_TO_C;
+        self.car.suspension_lb.wheel.brake_disk.temperature -= KELVIN_TO_C;
+        self.car.suspension_rb.wheel.brake_disk.temperature -= KELVIN_TO_C;
+        self.car.suspension_lf.wheel.brake_disk.layer_temperature -= KELVIN_TO_C;
+        self.car.suspension_rf.wheel.brake_disk.layer_temperature -= KELVIN_TO_C;
+        self.car.suspension_lb.wheel.brake_disk.layer_temperature -= KELVIN_TO_C;
+        self.car.suspension_rb.wheel.brake_disk.layer_temperature -= KELVIN_TO_C;
+        self.car.suspension_lf.wheel.tire.convert_to_celsius();
+        self.car.suspension_rf.wheel.tire.convert_to_celsius();
+        self.car.suspension_lb.wheel.tire.convert_to_celsius();
+        self.car.suspension_rb.wheel.tire.convert_to_celsius();
+    }
+    
+    pub fn get_time(&self) -> Time {
+        // https://www.inchcalculator.com/seconds-to-time-calculator/
+        let race_time: &f32 = &self.stage.race_time;
+        let mut time: Time = Default::default();
+        let hr = race_time / 3600.0;
+        time.hours = hr.floor();
+        let min = hr.fract() * 60.0;
+        time.minutes = min.floor();
+        time.seconds = f32::trunc((min.fract() * 60.0) * 100.0) / 100.0;
+        time
+    }
+}
+
+
+pub struct Time {
+    pub seconds: f32,
+    pub minutes: f32,
+    pub hours: f32,
+}
+impl Default for Time {
+    fn default() -> Self {
+        Time {
+            seconds: 0.0,
+            minutes: 0.0,
+            hours: 0.0,
+        }
+    }
+}
+
+pub fn format_time(minutes: f32, seconds: f32) -> String {
+    let mut time: String = String::new(); 
+    if minutes < 10.0 {
+        time.push_str(format!("0{}", minutes).as_str());
+    } else {
+        time.push_str(format!("{}", minutes).as_str());
+    }
+    time.push(':');
+    if seconds < 10.0 {
+        time.push_str(format!("0{}", seconds).as_str());
+    } else {
+        time.push_str(format!("{}", seconds).as_str());
+    }
+    let remainder = (seconds.fract() * 100.0).round();
+    if remainder == 0.0 {
+        time.push_str(".00");
+    } else if remainder % 10.0 == 0.0 {
+        time.push('0');
+    }
+    time
+}
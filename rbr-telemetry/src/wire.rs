@@ -0,0 +1,385 @@
+// Little endian wire layouts of the telemetry packets.
+//
+// Every layout is a hand-written table of fields with their byte offset, so decoding never
+// depends on how a serializer happens to lay out `Telemetry`. Each field knows how to read
+// and write itself on `Telemetry`, which is also what `encode` uses to build packets.
+
+// telemetry.rs
+use crate::telemetry::{DecodeError, Telemetry};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    F32,
+    I32,
+    U32,
+    I16,
+}
+impl FieldKind {
+    pub const fn size(self) -> usize {
+        match self {
+            FieldKind::F32 | FieldKind::I32 | FieldKind::U32 => 4,
+            FieldKind::I16 => 2,
+        }
+    }
+}
+
+pub struct Field {
+    pub name: &'static str,
+    pub offset: usize,
+    pub kind: FieldKind,
+    // wire value
+    pub get: fn(&Telemetry) -> f64,
+    pub set: fn(&mut Telemetry, f64),
+    // where it ends up in Telemetry, the same in every layout, and what it holds there
+    pub path: &'static str,
+    pub value: fn(&Telemetry) -> f32,
+}
+impl Field {
+    fn read(&self, data: &[u8]) -> Result<f64, DecodeError> {
+        let bytes = data
+            .get(self.offset..self.offset + self.kind.size())
+            .ok_or_else(|| DecodeError::Layout(format!("{} is past the end of the packet", self.name)))?;
+        Ok(match self.kind {
+            FieldKind::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            FieldKind::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            FieldKind::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            FieldKind::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        })
+    }
+
+    fn write(&self, data: &mut [u8], value: f64) {
+        let bytes = &mut data[self.offset..self.offset + self.kind.size()];
+        match self.kind {
+            FieldKind::F32 => bytes.copy_from_slice(&(value as f32).to_le_bytes()),
+            FieldKind::I32 => bytes.copy_from_slice(&(value as i32).to_le_bytes()),
+            FieldKind::U32 => bytes.copy_from_slice(&(value as u32).to_le_bytes()),
+            FieldKind::I16 => bytes.copy_from_slice(&(value as i16).to_le_bytes()),
+        }
+    }
+}
+
+pub struct Layout {
+    pub name: &'static str,
+    pub version: u16, // stored in session files
+    pub size: usize,
+    pub sections: &'static [&'static [Field]],
+}
+impl Layout {
+    pub fn fields(&self) -> impl Iterator<Item = &Field> {
+        self.sections.iter().flat_map(|section| section.iter())
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields().find(|f| f.name == name)
+    }
+
+    // Raw values only, no validation or unit conversion.
+    pub fn decode(&self, data: &[u8]) -> Result<Telemetry, DecodeError> {
+        if data.len() != self.size {
+            return Err(DecodeError::WrongSize {
+                expected: self.size,
+                actual: data.len(),
+            });
+        }
+        let mut telemetry = Telemetry::default();
+        for field in self.fields() {
+            (field.set)(&mut telemetry, field.read(data)?);
+        }
+        Ok(telemetry)
+    }
+
+    // Builds a packet from raw (unformatted) values, fields missing from the layout are dropped.
+    pub fn encode(&self, telemetry: &Telemetry) -> Vec<u8> {
+        let mut data = vec![0; self.size];
+        for field in self.fields() {
+            field.write(&mut data, (field.get)(telemetry));
+        }
+        data
+    }
+}
+
+macro_rules! field {
+    ($name:expr, $offset:expr, $kind:ident, $($path:ident).+) => {
+        Field {
+            name: $name,
+            offset: $offset,
+            kind: FieldKind::$kind,
+            get: |t| t.$($path).+ as f64,
+            set: |t, v| t.$($path).+ = v as _,
+            path: stringify!($($path).+),
+            value: |t| t.$($path).+ as f32,
+        }
+    };
+}
+
+// Signed fixed point with 21 fractional bits, stored in Telemetry as the plain value.
+macro_rules! fixed_field {
+    ($name:expr, $offset:expr, $($path:ident).+) => {
+        Field {
+            name: $name,
+            offset: $offset,
+            kind: FieldKind::I32,
+            get: |t| t.$($path).+ as f64 * FIXED_ONE,
+            set: |t, v| t.$($path).+ = (v / FIXED_ONE) as _,
+            path: stringify!($($path).+),
+            value: |t| t.$($path).+,
+        }
+    };
+}
+
+const FIXED_ONE: f64 = (1 << 21) as f64;
+
+// In the packet, but not decoded into Telemetry.
+macro_rules! unused_field {
+    ($name:expr, $offset:expr, $kind:ident) => {
+        Field {
+            name: $name,
+            offset: $offset,
+            kind: FieldKind::$kind,
+            get: |_| 0.0,
+            set: |_, _| {},
+            path: "",
+            value: |_| 0.0,
+        }
+    };
+}
+
+// rbr.telemetry.data.TelemetryData.h, 664 bytes, what the NGP plugin sends.
+
+const TD_HEADER: [Field; 14] = [
+    field!("totalSteps", 0, U32, total_steps),
+    field!("stage.index", 4, I32, stage.index),
+    field!("stage.progress", 8, F32, stage.progress),
+    field!("stage.raceTime", 12, F32, stage.race_time),
+    field!("stage.driveLineLocation", 16, F32, stage.drive_line_location),
+    field!("stage.distanceToEnd", 20, F32, stage.distance_to_end),
+    field!("control.steering", 24, F32, control.steering),
+    field!("control.throttle", 28, F32, control.throttle),
+    field!("control.brake", 32, F32, control.brake),
+    field!("control.handbrake", 36, F32, control.handbrake),
+    field!("control.clutch", 40, F32, control.clutch),
+    field!("control.gear", 44, I32, control.gear),
+    field!("control.footbrakePressure", 48, F32, control.footbrake_pressure),
+    field!("control.handbrakePressure", 52, F32, control.handbrake_pressure),
+];
+
+const TD_CAR: [Field; 24] = [
+    field!("car.index", 56, I32, car.index),
+    field!("car.speed", 60, F32, car.speed),
+    field!("car.positionX", 64, F32, car.position_x),
+    field!("car.positionY", 68, F32, car.position_y),
+    field!("car.positionZ", 72, F32, car.position_z),
+    field!("car.roll", 76, F32, car.roll),
+    field!("car.pitch", 80, F32, car.pitch),
+    field!("car.yaw", 84, F32, car.yaw),
+    field!("car.velocities.surge", 88, F32, car.velocities.surge),
+    field!("car.velocities.sway", 92, F32, car.velocities.sway),
+    field!("car.velocities.heave", 96, F32, car.velocities.heave),
+    field!("car.velocities.roll", 100, F32, car.velocities.roll),
+    field!("car.velocities.pitch", 104, F32, car.velocities.pitch),
+    field!("car.velocities.yaw", 108, F32, car.velocities.yaw),
+    field!("car.accelerations.surge", 112, F32, car.accelerations.surge),
+    field!("car.accelerations.sway", 116, F32, car.accelerations.sway),
+    field!("car.accelerations.heave", 120, F32, car.accelerations.heave),
+    field!("car.accelerations.roll", 124, F32, car.accelerations.roll),
+    field!("car.accelerations.pitch", 128, F32, car.accelerations.pitch),
+    field!("car.accelerations.yaw", 132, F32, car.accelerations.yaw),
+    field!("car.engine.rpm", 136, F32, car.engine.rpm),
+    field!("car.engine.radiatorCoolantTemperature", 140, F32, car.engine.radiator_coolant_temperature),
+    field!("car.engine.engineCoolantTemperature", 144, F32, car.engine.engine_coolant_temperature),
+    field!("car.engine.engineTemperature", 148, F32, car.engine.engine_temperature),
+];
+
+macro_rules! td_suspension {
+    ($prefix:literal, $corner:ident, $base:expr) => {
+        [
+            field!(concat!($prefix, ".springDeflection"), $base, F32, car.$corner.spring_deflection),
+            field!(concat!($prefix, ".rollbarForce"), $base + 4, F32, car.$corner.rollbar_force),
+            field!(concat!($prefix, ".springForce"), $base + 8, F32, car.$corner.spring_force),
+            field!(concat!($prefix, ".damperForce"), $base + 12, F32, car.$corner.damper_force),
+            field!(concat!($prefix, ".strutForce"), $base + 16, F32, car.$corner.strut_force),
+            field!(concat!($prefix, ".helperSpringIsActive"), $base + 20, I32, car.$corner.helper_spring_is_active),
+            field!(concat!($prefix, ".damper.damage"), $base + 24, F32, car.$corner.damper.damage),
+            field!(concat!($prefix, ".damper.pistonVelocity"), $base + 28, F32, car.$corner.damper.piston_velocity),
+            field!(concat!($prefix, ".brakeDisk.layerTemperature"), $base + 32, F32, car.$corner.wheel.brake_disk.layer_temperature),
+            field!(concat!($prefix, ".brakeDisk.temperature"), $base + 36, F32, car.$corner.wheel.brake_disk.temperature),
+            field!(concat!($prefix, ".brakeDisk.wear"), $base + 40, F32, car.$corner.wheel.brake_disk.wear),
+            field!(concat!($prefix, ".tire.pressure"), $base + 44, F32, car.$corner.wheel.tire.pressure),
+            field!(concat!($prefix, ".tire.temperature"), $base + 48, F32, car.$corner.wheel.tire.temperature),
+            field!(concat!($prefix, ".tire.carcassTemperature"), $base + 52, F32, car.$corner.wheel.tire.carcass_temperature),
+            field!(concat!($prefix, ".tire.treadTemperature"), $base + 56, F32, car.$corner.wheel.tire.tread_temperature),
+            field!(concat!($prefix, ".tire.currentSegment"), $base + 60, U32, car.$corner.wheel.tire.current_segment),
+            field!(concat!($prefix, ".tire.segment1.temperature"), $base + 64, F32, car.$corner.wheel.tire.segment1.temperature),
+            field!(concat!($prefix, ".tire.segment1.wear"), $base + 68, F32, car.$corner.wheel.tire.segment1.wear),
+            field!(concat!($prefix, ".tire.segment2.temperature"), $base + 72, F32, car.$corner.wheel.tire.segment2.temperature),
+            field!(concat!($prefix, ".tire.segment2.wear"), $base + 76, F32, car.$corner.wheel.tire.segment2.wear),
+            field!(concat!($prefix, ".tire.segment3.temperature"), $base + 80, F32, car.$corner.wheel.tire.segment3.temperature),
+            field!(concat!($prefix, ".tire.segment3.wear"), $base + 84, F32, car.$corner.wheel.tire.segment3.wear),
+            field!(concat!($prefix, ".tire.segment4.temperature"), $base + 88, F32, car.$corner.wheel.tire.segment4.temperature),
+            field!(concat!($prefix, ".tire.segment4.wear"), $base + 92, F32, car.$corner.wheel.tire.segment4.wear),
+            field!(concat!($prefix, ".tire.segment5.temperature"), $base + 96, F32, car.$corner.wheel.tire.segment5.temperature),
+            field!(concat!($prefix, ".tire.segment5.wear"), $base + 100, F32, car.$corner.wheel.tire.segment5.wear),
+            field!(concat!($prefix, ".tire.segment6.temperature"), $base + 104, F32, car.$corner.wheel.tire.segment6.temperature),
+            field!(concat!($prefix, ".tire.segment6.wear"), $base + 108, F32, car.$corner.wheel.tire.segment6.wear),
+            field!(concat!($prefix, ".tire.segment7.temperature"), $base + 112, F32, car.$corner.wheel.tire.segment7.temperature),
+            field!(concat!($prefix, ".tire.segment7.wear"), $base + 116, F32, car.$corner.wheel.tire.segment7.wear),
+            field!(concat!($prefix, ".tire.segment8.temperature"), $base + 120, F32, car.$corner.wheel.tire.segment8.temperature),
+            field!(concat!($prefix, ".tire.segment8.wear"), $base + 124, F32, car.$corner.wheel.tire.segment8.wear),
+        ]
+    };
+}
+
+const TD_LF: [Field; 32] = td_suspension!("car.suspensionLF", suspension_lf, 152);
+const TD_RF: [Field; 32] = td_suspension!("car.suspensionRF", suspension_rf, 280);
+const TD_LB: [Field; 32] = td_suspension!("car.suspensionLB", suspension_lb, 408);
+const TD_RB: [Field; 32] = td_suspension!("car.suspensionRB", suspension_rb, 536);
+
+pub const TELEMETRY_DATA_LAYOUT: Layout = Layout {
+    name: "TelemetryData",
+    version: 1,
+    size: 664,
+    sections: &[&TD_HEADER, &TD_CAR, &TD_LF, &TD_RF, &TD_LB, &TD_RB],
+};
+
+// documentation/telemetry_documentation.txt, 706 bytes. Packed, so everything after
+// the 16 bit gear is unaligned. It has no stage, car or handbrake index, and no damper
+// damage. The brake disk temperatures are documented as integers, but the samples
+// (667865728, 672377536, ...) only make sense as fixed point: divided by 2^21 they are
+// 318-321 K, next to the 319.5 K of the tire segments in the same packet. The wear
+// sample is 0, so its encoding can't be told and it isn't decoded.
+
+const DOC_HEADER: [Field; 12] = [
+    field!("progress", 0, F32, stage.progress),
+    field!("raceTime", 4, F32, stage.race_time),
+    field!("driveLineLocation", 8, F32, stage.drive_line_location),
+    field!("travelDistance", 12, F32, stage.travel_distance),
+    field!("distanceToEnd", 16, F32, stage.distance_to_end),
+    field!("steering", 20, F32, control.steering),
+    field!("throttle", 24, F32, control.throttle),
+    field!("brake", 28, F32, control.brake),
+    field!("clutch", 32, F32, control.clutch),
+    field!("gear", 36, I16, control.gear),
+    field!("footbrakePressure", 38, F32, control.footbrake_pressure),
+    field!("handbrakePressure", 42, F32, control.handbrake_pressure),
+];
+
+const DOC_CAR: [Field; 41] = [
+    field!("engineRotation", 46, F32, car.engine.rpm),
+    field!("speed", 50, F32, car.speed),
+    field!("radiatorCoolantHeatState.temperature", 54, F32, car.engine.radiator_coolant_temperature),
+    field!("engineCoolantHeatState.temperature", 58, F32, car.engine.engine_coolant_temperature),
+    field!("engineTemperature", 62, F32, car.engine.engine_temperature),
+    field!("position.x", 66, F32, car.position_x),
+    field!("position.y", 70, F32, car.position_y),
+    field!("position.z", 74, F32, car.position_z),
+    field!("vecRelativeLinearVelocity.x", 78, F32, car.relative_linear_velocity.x),
+    field!("vecRelativeLinearVelocity.y", 82, F32, car.relative_linear_velocity.y),
+    field!("vecRelativeLinearVelocity.z", 86, F32, car.relative_linear_velocity.z),
+    field!("vecRelativeAngularVelocity.x", 90, F32, car.relative_angular_velocity.x),
+    field!("vecRelativeAngularVelocity.y", 94, F32, car.relative_angular_velocity.y),
+    field!("vecRelativeAngularVelocity.z", 98, F32, car.relative_angular_velocity.z),
+    field!("vecRelativeLinearAcceleration.x", 102, F32, car.relative_linear_acceleration.x),
+    field!("vecRelativeLinearAcceleration.y", 106, F32, car.relative_linear_acceleration.y),
+    field!("vecRelativeLinearAcceleration.z", 110, F32, car.relative_linear_acceleration.z),
+    field!("vecRelativeAngularAcceleration.x", 114, F32, car.relative_angular_acceleration.x),
+    field!("vecRelativeAngularAcceleration.y", 118, F32, car.relative_angular_acceleration.y),
+    field!("vecRelativeAngularAcceleration.z", 122, F32, car.relative_angular_acceleration.z),
+    field!("vecLinearVelocityCar.x", 126, F32, car.linear_velocity.x),
+    field!("vecLinearVelocityCar.y", 130, F32, car.linear_velocity.y),
+    field!("vecLinearVelocityCar.z", 134, F32, car.linear_velocity.z),
+    field!("vecAngularVelocityCar.x", 138, F32, car.angular_velocity.x),
+    field!("vecAngularVelocityCar.y", 142, F32, car.angular_velocity.y),
+    field!("vecAngularVelocityCar.z", 146, F32, car.angular_velocity.z),
+    field!("vecLinearAccelerationCar.x", 150, F32, car.linear_acceleration.x),
+    field!("vecLinearAccelerationCar.y", 154, F32, car.linear_acceleration.y),
+    field!("vecLinearAccelerationCar.z", 158, F32, car.linear_acceleration.z),
+    field!("vecAngularAccelerationCar.x", 162, F32, car.angular_acceleration.x),
+    field!("vecAngularAccelerationCar.y", 166, F32, car.angular_acceleration.y),
+    field!("vecAngularAccelerationCar.z", 170, F32, car.angular_acceleration.z),
+    field!("roll", 174, F32, car.roll),
+    field!("pitch", 178, F32, car.pitch),
+    field!("yaw", 182, F32, car.yaw),
+    field!("vecAvgLinearAccelerationCar.x", 186, F32, car.avg_linear_acceleration.x),
+    field!("vecAvgLinearAccelerationCar.y", 190, F32, car.avg_linear_acceleration.y),
+    field!("vecAvgLinearAccelerationCar.z", 194, F32, car.avg_linear_acceleration.z),
+    field!("vecAvgAngularAccelerationCar.x", 198, F32, car.avg_angular_acceleration.x),
+    field!("vecAvgAngularAccelerationCar.y", 202, F32, car.avg_angular_acceleration.y),
+    field!("vecAvgAngularAccelerationCar.z", 206, F32, car.avg_angular_acceleration.z),
+];
+
+// deflectionVelocity is the damper piston velocity, tyreTemperature the carcass temperature.
+macro_rules! doc_wheel {
+    ($prefix:literal, $corner:ident, $base:expr) => {
+        [
+            fixed_field!(concat!($prefix, ".brakeDiskLayerTemp"), $base, car.$corner.wheel.brake_disk.layer_temperature),
+            fixed_field!(concat!($prefix, ".brakeDiskTemp"), $base + 4, car.$corner.wheel.brake_disk.temperature),
+            unused_field!(concat!($prefix, ".brakeWear"), $base + 8, I32),
+            field!(concat!($prefix, ".deflection"), $base + 12, F32, car.$corner.spring_deflection),
+            field!(concat!($prefix, ".deflectionVelocity"), $base + 16, F32, car.$corner.damper.piston_velocity),
+            field!(concat!($prefix, ".temperature"), $base + 20, F32, car.$corner.wheel.tire.temperature),
+            field!(concat!($prefix, ".pressure"), $base + 24, F32, car.$corner.wheel.tire.pressure),
+            field!(concat!($prefix, ".segmentData[0].temperature"), $base + 28, F32, car.$corner.wheel.tire.segment1.temperature),
+            field!(concat!($prefix, ".segmentData[1].temperature"), $base + 32, F32, car.$corner.wheel.tire.segment2.temperature),
+            field!(concat!($prefix, ".segmentData[2].temperature"), $base + 36, F32, car.$corner.wheel.tire.segment3.temperature),
+            field!(concat!($prefix, ".segmentData[3].temperature"), $base + 40, F32, car.$corner.wheel.tire.segment4.temperature),
+            field!(concat!($prefix, ".segmentData[4].temperature"), $base + 44, F32, car.$corner.wheel.tire.segment5.temperature),
+            field!(concat!($prefix, ".segmentData[5].temperature"), $base + 48, F32, car.$corner.wheel.tire.segment6.temperature),
+            field!(concat!($prefix, ".segmentData[6].temperature"), $base + 52, F32, car.$corner.wheel.tire.segment7.temperature),
+            field!(concat!($prefix, ".segmentData[7].temperature"), $base + 56, F32, car.$corner.wheel.tire.segment8.temperature),
+            field!(concat!($prefix, ".treadTemperature"), $base + 60, F32, car.$corner.wheel.tire.tread_temperature),
+            field!(concat!($prefix, ".tyreTemperature"), $base + 64, F32, car.$corner.wheel.tire.carcass_temperature),
+            field!(concat!($prefix, ".currentTyreSegment"), $base + 68, I32, car.$corner.wheel.tire.current_segment),
+            field!(concat!($prefix, ".rollbarForce"), $base + 72, F32, car.$corner.rollbar_force),
+            field!(concat!($prefix, ".springForce"), $base + 76, F32, car.$corner.spring_force),
+            field!(concat!($prefix, ".dampingForce"), $base + 80, F32, car.$corner.damper_force),
+            field!(concat!($prefix, ".strutForce"), $base + 84, F32, car.$corner.strut_force),
+            field!(concat!($prefix, ".helperSpringActive"), $base + 88, I32, car.$corner.helper_spring_is_active),
+            field!(concat!($prefix, ".wear[0]"), $base + 92, F32, car.$corner.wheel.tire.segment1.wear),
+            field!(concat!($prefix, ".wear[1]"), $base + 96, F32, car.$corner.wheel.tire.segment2.wear),
+            field!(concat!($prefix, ".wear[2]"), $base + 100, F32, car.$corner.wheel.tire.segment3.wear),
+            field!(concat!($prefix, ".wear[3]"), $base + 104, F32, car.$corner.wheel.tire.segment4.wear),
+            field!(concat!($prefix, ".wear[4]"), $base + 108, F32, car.$corner.wheel.tire.segment5.wear),
+            field!(concat!($prefix, ".wear[5]"), $base + 112, F32, car.$corner.wheel.tire.segment6.wear),
+            field!(concat!($prefix, ".wear[6]"), $base + 116, F32, car.$corner.wheel.tire.segment7.wear),
+            field!(concat!($prefix, ".wear[7]"), $base + 120, F32, car.$corner.wheel.tire.segment8.wear),
+        ]
+    };
+}
+
+const DOC_LF: [Field; 31] = doc_wheel!("LF", suspension_lf, 210);
+const DOC_RF: [Field; 31] = doc_wheel!("RF", suspension_rf, 334);
+const DOC_LB: [Field; 31] = doc_wheel!("LB", suspension_lb, 458);
+const DOC_RB: [Field; 31] = doc_wheel!("RB", suspension_rb, 582);
+
+pub const DOCUMENTED_LAYOUT: Layout = Layout {
+    name: "Documented",
+    version: 2,
+    size: 706,
+    sections: &[&DOC_HEADER, &DOC_CAR, &DOC_LF, &DOC_RF, &DOC_LB, &DOC_RB],
+};
+
+// Every layout the decoder knows about. None of them carries a version field, so they are
+// told apart by packet size; a new plugin build that changes the size gets its own entry.
+pub const LAYOUTS: [&Layout; 2] = [&TELEMETRY_DATA_LAYOUT, &DOCUMENTED_LAYOUT];
+
+pub const MAX_PACKET_SIZE: usize = max_packet_size();
+
+const fn max_packet_size() -> usize {
+    let mut size = 0;
+    let mut i = 0;
+    while i < LAYOUTS.len() {
+        if LAYOUTS[i].size > size {
+            size = LAYOUTS[i].size;
+        }
+        i += 1;
+    }
+    size
+}
+
+pub fn layout_by_version(version: u16) -> Option<&'static Layout> {
+    LAYOUTS.into_iter().find(|l| l.version == version)
+}
+
+pub fn layouts_with_size(size: usize) -> impl Iterator<Item = &'static Layout> {
+    LAYOUTS.into_iter().filter(move |l| l.size == size)
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use common::*;
+use rbr_telemetry::*;
+
+const LENGTH: f32 = 100.0;
+
+fn on_stage(stage: i32, time: f32, progress: f32) -> Frame {
+    frame(time).stage(stage).progress(progress).distance_to_end(LENGTH - progress)
+}
+
+// drives the stage at a constant speed, returns whether the finish was a new best
+fn drive(bests: &mut PersonalBests, stage: i32, speed: f32) -> bool {
+    let mut new_best = false;
+    for step in 1..=10 {
+        let progress = step as f32 * 10.0;
+        new_best |= bests.add(&on_stage(stage, progress / speed, progress)).is_some();
+    }
+    new_best
+}
+
+#[test]
+fn only_finished_runs_count() {
+    let mut bests = PersonalBests::default();
+    for step in 1..=5 {
+        let progress = step as f32 * 10.0;
+        assert!(bests.add(&on_stage(1, progress, progress)).is_none());
+    }
+    assert!(bests.best(1, 0).is_none());
+    assert!(drive(&mut bests, 1, 10.0), "restart, then finished");
+    assert_eq!(bests.best(1, 0).unwrap().end_time(), 10.0);
+}
+
+#[test]
+fn keeps_the_quickest_run_per_stage() {
+    let mut bests = PersonalBests::default();
+    assert!(drive(&mut bests, 1, 10.0));
+    assert!(!drive(&mut bests, 1, 5.0), "slower");
+    assert!(drive(&mut bests, 1, 20.0), "quicker");
+    assert!(drive(&mut bests, 2, 5.0), "another stage");
+    assert_eq!(bests.best(1, 0).unwrap().end_time(), 5.0);
+    assert_eq!(bests.best(2, 0).unwrap().end_time(), 20.0);
+    assert_eq!(bests.len(), 2);
+}
+
+#[test]
+fn delta_against_the_best_at_the_same_distance() {
+    let mut bests = PersonalBests::default();
+    assert!(bests.delta(&on_stage(1, 1.0, 10.0)).is_none());
+    drive(&mut bests, 1, 10.0);
+    assert_eq!(bests.delta(&on_stage(1, 6.0, 50.0)), Some(1.0));
+    assert_eq!(bests.delta(&on_stage(1, 4.5, 50.0)), Some(-0.5));
+    assert!(bests.delta(&on_stage(2, 4.5, 50.0)).is_none());
+}
+
+#[test]
+fn a_run_joined_halfway_does_not_count() {
+    let mut bests = PersonalBests::default();
+    for step in 5..=10 {
+        let progress = step as f32 * 10.0;
+        assert!(bests.add(&on_stage(1, progress, progress)).is_none());
+    }
+    assert!(bests.best(1, 0).is_none());
+}
+
+#[test]
+fn a_run_with_missing_packets_does_not_count() {
+    let mut bests = PersonalBests::default();
+    let length = 1000.0;
+    for progress in [10.0, 20.0, 30.0, length] {
+        let telemetry = frame(progress).stage(1).progress(progress).distance_to_end(length - progress);
+        assert!(bests.add(&telemetry).is_none());
+    }
+    assert!(bests.best(1, 0).is_none());
+}
+
+#[test]
+fn best_files_round_trip_and_check_the_header() {
+    let mut bests = PersonalBests::default();
+    drive(&mut bests, 3, 10.0);
+    let run = bests.best(3, 0).unwrap();
+    let data = encode_best(run).unwrap();
+    assert_eq!(&decode_best(&data).unwrap(), run);
+    assert_eq!(&data[..4], b"RBRB");
+
+    let mut other = data.clone();
+    other[..4].copy_from_slice(b"RBRS");
+    assert_eq!(decode_best(&other).err(), Some("not a best run file".to_string()));
+    let mut newer = data.clone();
+    newer[4] = 2;
+    assert_eq!(decode_best(&newer).err(), Some("unsupported best run version 2".to_string()));
+    // a file from before the header
+    assert!(decode_best(&bincode::serialize(run).unwrap()).is_err());
+}
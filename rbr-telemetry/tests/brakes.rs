@@ -0,0 +1,66 @@
+mod common;
+
+use common::*;
+use rbr_telemetry::*;
+
+const FADE: f32 = 600.0;
+
+#[test]
+fn fade_time_counts_from_crossing_the_temperature() {
+    let mut brakes = BrakeMonitor::default();
+    brakes.add(&frame(10.0).disks([500.0, 500.0, 300.0, 300.0]), FADE);
+    brakes.add(&frame(11.0).disks([650.0, 500.0, 300.0, 300.0]), FADE);
+    brakes.add(&frame(14.5).disks([700.0, 500.0, 300.0, 300.0]), FADE);
+    assert_eq!(brakes.hot_for(0), 3.5);
+    assert_eq!(brakes.hot_for(1), 0.0);
+
+    // cooling below it starts over
+    brakes.add(&frame(15.0).disks([590.0, 500.0, 300.0, 300.0]), FADE);
+    assert_eq!(brakes.hot_for(0), 0.0);
+    brakes.add(&frame(16.0).disks([610.0, 500.0, 300.0, 300.0]), FADE);
+    brakes.add(&frame(17.0).disks([610.0, 500.0, 300.0, 300.0]), FADE);
+    assert_eq!(brakes.hot_for(0), 1.0);
+}
+
+#[test]
+fn restart_resets_fade_and_bias() {
+    let mut brakes = BrakeMonitor::default();
+    brakes.add(&frame(10.0).disks([700.0, 700.0, 300.0, 300.0]).footbrake_pressure(BRAKING_PRESSURE * 2.0), FADE);
+    brakes.add(&frame(20.0).disks([720.0, 720.0, 310.0, 310.0]).footbrake_pressure(BRAKING_PRESSURE * 2.0), FADE);
+    assert!(brakes.hot_for(0) > 0.0);
+    assert!(brakes.bias().is_some());
+
+    brakes.add(&frame(0.0).disks([700.0, 700.0, 300.0, 300.0]), FADE);
+    assert_eq!(brakes.hot_for(0), 0.0);
+    assert_eq!(brakes.bias(), None);
+}
+
+#[test]
+fn bias_follows_disk_heating_under_the_footbrake() {
+    let mut brakes = BrakeMonitor::default();
+    let mut disks = [100.0; 4];
+    brakes.add(&frame(1.0).disks(disks), FADE);
+    for i in 0..10 {
+        // fronts heat up three times as fast as the rears
+        disks = [disks[0] + 3.0, disks[1] + 3.0, disks[2] + 1.0, disks[3] + 1.0];
+        brakes.add(&frame(2.0 + i as f32).disks(disks).footbrake_pressure(BRAKING_PRESSURE * 2.0), FADE);
+    }
+    assert_eq!(brakes.bias(), Some(0.75));
+
+    // heating off the footbrake, or with the handbrake pulled, doesn't count
+    let pulled = frame(20.0)
+        .disks([disks[0], disks[1], disks[2] + 50.0, disks[3] + 50.0])
+        .footbrake_pressure(BRAKING_PRESSURE * 2.0)
+        .handbrake(100.0);
+    brakes.add(&pulled, FADE);
+    brakes.add(&frame(21.0).disks([disks[0], disks[1], disks[2] + 100.0, disks[3] + 100.0]), FADE);
+    assert_eq!(brakes.bias(), Some(0.75));
+}
+
+#[test]
+fn bias_waits_for_enough_heat() {
+    let mut brakes = BrakeMonitor::default();
+    brakes.add(&frame(1.0).disks([100.0; 4]).footbrake_pressure(BRAKING_PRESSURE * 2.0), FADE);
+    brakes.add(&frame(2.0).disks([101.0, 101.0, 100.5, 100.5]).footbrake_pressure(BRAKING_PRESSURE * 2.0), FADE);
+    assert_eq!(brakes.bias(), None);
+}
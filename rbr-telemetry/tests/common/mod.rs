@@ -0,0 +1,112 @@
+// Telemetry frames for the tests, built up from the fields a test cares about.
+// Every test binary compiles this module and uses a different part of it.
+#![allow(dead_code)]
+
+use std::ops::Deref;
+
+use rbr_telemetry::*;
+
+pub struct Frame(pub Telemetry);
+
+// A frame at `race_time` seconds, everything else zero.
+pub fn frame(race_time: f32) -> Frame {
+    let mut telemetry = Telemetry::default();
+    telemetry.stage.race_time = race_time;
+    Frame(telemetry)
+}
+
+impl Frame {
+    pub fn stage(mut self, index: i32) -> Self {
+        self.0.stage.index = index;
+        self
+    }
+
+    pub fn car(mut self, index: i32) -> Self {
+        self.0.car.index = index;
+        self
+    }
+
+    pub fn progress(mut self, meters: f32) -> Self {
+        self.0.stage.progress = meters;
+        self
+    }
+
+    pub fn distance_to_end(mut self, meters: f32) -> Self {
+        self.0.stage.distance_to_end = meters;
+        self
+    }
+
+    pub fn throttle(mut self, throttle: f32) -> Self {
+        self.0.control.throttle = throttle;
+        self
+    }
+
+    pub fn footbrake_pressure(mut self, pressure: f32) -> Self {
+        self.0.control.footbrake_pressure = pressure;
+        self
+    }
+
+    pub fn handbrake(mut self, handbrake: f32) -> Self {
+        self.0.control.handbrake = handbrake;
+        self
+    }
+
+    pub fn coolant(mut self, temperature: f32) -> Self {
+        self.0.car.engine.engine_coolant_temperature = temperature;
+        self
+    }
+
+    pub fn engine_temperature(mut self, temperature: f32) -> Self {
+        self.0.car.engine.engine_temperature = temperature;
+        self
+    }
+
+    // LF, RF, LB, RB
+    pub fn tire_pressure(mut self, corner: usize, pressure: f32) -> Self {
+        self.suspension(corner).wheel.tire.pressure = pressure;
+        self
+    }
+
+    pub fn disks(mut self, temperatures: [f32; 4]) -> Self {
+        for (corner, temperature) in temperatures.into_iter().enumerate() {
+            self.suspension(corner).wheel.brake_disk.temperature = temperature;
+        }
+        self
+    }
+
+    fn suspension(&mut self, corner: usize) -> &mut Suspension {
+        let car = &mut self.0.car;
+        match corner {
+            0 => &mut car.suspension_lf,
+            1 => &mut car.suspension_rf,
+            2 => &mut car.suspension_lb,
+            _ => &mut car.suspension_rb,
+        }
+    }
+
+    pub fn build(self) -> Telemetry {
+        self.0
+    }
+}
+impl Deref for Frame {
+    type Target = Telemetry;
+
+    fn deref(&self) -> &Telemetry {
+        &self.0
+    }
+}
+
+// Raw frames recorded back to back, 10 ms apart.
+pub fn session(stage: i32, car: i32, frames: impl IntoIterator<Item = Telemetry>) -> Session {
+    let frames = frames.into_iter()
+        .enumerate()
+        .map(|(i, telemetry)| SessionFrame {
+            time_us: i as u64 * 10_000,
+            data: TELEMETRY_DATA_LAYOUT.encode(&telemetry),
+        })
+        .collect();
+    Session {
+        header: SessionHeader::new(stage, car, TELEMETRY_DATA_LAYOUT.version),
+        frames,
+    }
+}
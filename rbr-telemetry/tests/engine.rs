@@ -0,0 +1,35 @@
+mod common;
+
+use common::*;
+use rbr_telemetry::*;
+
+// the engine heats up twice as fast as the coolant
+fn warming(time: f32, coolant: f32) -> Frame {
+    frame(time).coolant(coolant).engine_temperature(coolant * 2.0)
+}
+
+#[test]
+fn rate_is_per_minute_once_half_the_window_is_filled() {
+    let mut trend = EngineTrend::new(60.0);
+    for t in 0..20 {
+        trend.add(&warming(t as f32, 90.0 + t as f32 * 0.1));
+    }
+    assert_eq!(trend.rate(), None);
+    for t in 20..=40 {
+        trend.add(&warming(t as f32, 90.0 + t as f32 * 0.1));
+    }
+    let (coolant, engine) = trend.rate().unwrap();
+    assert!((coolant - 6.0).abs() < 1e-3);
+    assert!((engine - 12.0).abs() < 1e-3);
+}
+
+#[test]
+fn restart_clears_the_trend() {
+    let mut trend = EngineTrend::new(10.0);
+    for t in 0..=10 {
+        trend.add(&warming(t as f32, 90.0 + t as f32));
+    }
+    assert!(trend.rate().is_some());
+    trend.add(&warming(1.0, 80.0));
+    assert_eq!(trend.rate(), None);
+}
@@ -0,0 +1,69 @@
+use rbr_telemetry::*;
+
+#[test]
+fn bins_are_symmetric_around_zero() {
+    let histogram = DamperHistogram::new(0.1, 10);
+    assert_eq!(histogram.bin(0.0), 5);
+    assert_eq!(histogram.bin(0.05), 5);
+    assert_eq!(histogram.bin(-0.05), 4);
+    assert_eq!(histogram.bin_range(5), (0.0, 0.1));
+    // outside the range goes into the outer bins
+    assert_eq!(histogram.bin(10.0), 9);
+    assert_eq!(histogram.bin(-10.0), 0);
+}
+
+#[test]
+fn splits_bump_and_rebound_at_the_knees() {
+    let mut histogram = DamperHistogram::new(0.01, 100);
+    for (i, v) in [0.02, 0.03, 0.2, -0.02, -0.3, -0.4, 0.01, 0.3].into_iter().enumerate() {
+        histogram.add(i as f32, [v; 4]);
+    }
+    let bands = histogram.bands(0, 0.1, 0.1);
+    assert_eq!(bands.low_bump, 3.0 / 8.0);
+    assert_eq!(bands.high_bump, 2.0 / 8.0);
+    assert_eq!(bands.low_rebound, 1.0 / 8.0);
+    assert_eq!(bands.high_rebound, 2.0 / 8.0);
+}
+
+#[test]
+fn rolling_window_forgets_old_samples() {
+    let mut histogram = DamperHistogram::new(0.1, 10);
+    histogram.set_window(Some(1.0));
+    histogram.add(0.0, [0.35; 4]);
+    histogram.add(0.5, [0.0; 4]);
+    histogram.add(1.4, [0.0; 4]);
+    assert_eq!(histogram.samples(), 2);
+    assert_eq!(histogram.counts(0)[histogram.bin(0.35)], 0);
+    assert_eq!(histogram.counts(0)[histogram.bin(0.0)], 2);
+
+    // a restart clears the stage
+    histogram.add(0.1, [0.0; 4]);
+    assert_eq!(histogram.samples(), 1);
+}
+
+#[test]
+fn frozen_race_time_is_counted_once() {
+    let mut histogram = DamperHistogram::new(0.1, 10);
+    histogram.add(1.0, [0.35; 4]);
+    // paused: the same frame keeps arriving
+    for _ in 0..100 {
+        histogram.add(1.0, [0.35; 4]);
+    }
+    histogram.add(1.02, [0.0; 4]);
+    assert_eq!(histogram.samples(), 2);
+    assert_eq!(histogram.counts(0)[histogram.bin(0.35)], 1);
+}
+
+#[test]
+fn exports_bin_counts_as_csv() {
+    let mut histogram = DamperHistogram::new(0.5, 4);
+    histogram.add(0.0, [0.1, -0.1, 0.6, -0.6]);
+    assert_eq!(
+        histogram.to_csv(),
+        "velocity_min,velocity_max,LF,RF,LB,RB\n\
+         -1.000,-0.500,0,0,0,1\n\
+         -0.500,0.000,0,1,0,0\n\
+         0.000,0.500,1,0,0,0\n\
+         0.500,1.000,0,0,1,0\n"
+    );
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::*;
+use rbr_telemetry::*;
+
+#[test]
+fn every_channel_is_stored_with_time_and_distance() {
+    let mut history = ChannelHistory::new(100, 10.0);
+    history.add(&frame(1.0).progress(20.0).throttle(50.0).tire_pressure(3, 100.0));
+    history.add(&frame(1.5).progress(30.0).throttle(60.0).tire_pressure(3, 120.0));
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.times(), &[1.0, 1.5]);
+    assert_eq!(history.distances(), &[20.0, 30.0]);
+    assert_eq!(history.values(Channel::THROTTLE), &[50.0, 60.0]);
+    let pressure = |corner| Channel::corner(corner, "wheel.tire.pressure").unwrap();
+    assert_eq!(history.latest(pressure(Corner::RB)), Some(120.0));
+    assert_eq!(history.latest(pressure(Corner::LF)), Some(0.0));
+}
+
+#[test]
+fn buffers_grow_with_the_frames_up_to_the_capacity() {
+    let mut history = ChannelHistory::new(1000, 100.0);
+    history.add(&frame(1.0));
+    assert!(history.times().capacity() < 100);
+    assert!(history.values(Channel::THROTTLE).capacity() < 100);
+    for i in 2..2000 {
+        history.add(&frame(i as f32 * 0.01));
+    }
+    assert_eq!(history.len(), 1000);
+    assert!(history.times().capacity() <= 1000);
+    assert!(history.values(Channel::GEAR).capacity() <= 1000);
+}
+
+#[test]
+fn never_grows_past_the_capacity() {
+    let mut history = ChannelHistory::new(5, 100.0);
+    for i in 0..20 {
+        history.add(&frame(i as f32).throttle(i as f32));
+    }
+    assert_eq!(history.len(), 5);
+    assert_eq!(history.values(Channel::THROTTLE), &[15.0, 16.0, 17.0, 18.0, 19.0]);
+}
+
+#[test]
+fn drops_frames_older_than_the_duration() {
+    let mut history = ChannelHistory::new(100, 2.0);
+    for i in 0..10 {
+        history.add(&frame(i as f32 * 0.5));
+    }
+    assert_eq!(history.times(), &[2.5, 3.0, 3.5, 4.0, 4.5]);
+    history.set_duration(1.0);
+    assert_eq!(history.times(), &[3.5, 4.0, 4.5]);
+}
+
+#[test]
+fn restart_clears_the_history() {
+    let mut history = ChannelHistory::new(100, 10.0);
+    history.add(&frame(5.0).progress(100.0).throttle(1.0));
+    history.add(&frame(0.0).throttle(2.0));
+    assert_eq!(history.values(Channel::THROTTLE), &[2.0]);
+}
+
+#[test]
+fn frozen_race_time_keeps_one_frame() {
+    let mut history = ChannelHistory::new(100, 2.0);
+    history.add(&frame(1.0).throttle(10.0));
+    // paused: packets keep coming with the same race time
+    for i in 0..500 {
+        history.add(&frame(1.5).throttle(i as f32));
+    }
+    assert_eq!(history.times(), &[1.0, 1.5]);
+    assert_eq!(history.latest(Channel::THROTTLE), Some(499.0));
+    history.add(&frame(4.0));
+    assert_eq!(history.times(), &[4.0]);
+}
+
+#[test]
+fn channels_are_unique() {
+    let all = Channel::all();
+    let channels: std::collections::HashSet<Channel> = all.iter().copied().collect();
+    assert_eq!(channels.len(), all.len());
+    let names: std::collections::HashSet<String> = all.iter().map(|c| c.name()).collect();
+    assert_eq!(names.len(), all.len());
+}
+
+#[test]
+fn every_decoded_field_is_a_channel() {
+    for layout in LAYOUTS {
+        for field in layout.fields().filter(|f| !f.path.is_empty()) {
+            let channel = Channel::find(field.path).unwrap();
+            assert_eq!(channel.path(), field.path);
+        }
+    }
+    // only in the documented layout
+    assert!(Channel::find("car.angular_velocity.z").is_some());
+    assert!(Channel::find("stage.travel_distance").is_some());
+    assert!(Channel::find("car.suspension_rb.wheel.tire.segment8.wear").is_some());
+    assert!(Channel::find("car.nothing").is_none());
+    for channel in Channel::PEDALS.into_iter().chain([Channel::SPEED, Channel::DISTANCE_TO_END]) {
+        assert_eq!(Channel::find(channel.path()), Some(channel));
+    }
+}
+
+#[test]
+fn channels_have_names_and_units() {
+    let name = |path| Channel::find(path).unwrap().name();
+    let unit = |path| Channel::find(path).unwrap().unit();
+    assert_eq!(name("control.throttle"), "Throttle");
+    assert_eq!(name("car.suspension_lf.wheel.tire.pressure"), "Tire pressure LF");
+    assert_eq!(name("car.index"), "Car index");
+    assert_eq!(name("stage.distance_to_end"), "Distance to end");
+    assert_eq!(unit("control.throttle"), "%");
+    assert_eq!(unit("car.suspension_rb.wheel.brake_disk.layer_temperature"), "°C");
+    assert_eq!(unit("car.velocities.yaw"), "rad/s");
+    assert_eq!(unit("car.linear_acceleration.x"), "m/s²");
+    assert_eq!(unit("car.suspension_lb.strut_force"), "N");
+}
+
+#[test]
+fn channels_are_stored_by_path() {
+    let channels = vec![Channel::THROTTLE, Channel::corner(Corner::LB, "damper.piston_velocity").unwrap()];
+    let data = bincode::serialize(&channels).unwrap();
+    assert_eq!(data, bincode::serialize(&vec!["control.throttle", "car.suspension_lb.damper.piston_velocity"]).unwrap());
+    assert_eq!(bincode::deserialize::<Vec<Channel>>(&data).unwrap(), channels);
+    let unknown = bincode::serialize(&vec!["car.nothing"]).unwrap();
+    assert!(bincode::deserialize::<Vec<Channel>>(&unknown).is_err());
+}
@@ -0,0 +1,161 @@
+// Checks the wire layouts field by field. The fixture is synthesized, not captured from
+// the game: it is the example packet listed in documentation/telemetry_documentation.txt,
+// encoded with the documented types. It proves the layout matches the documentation,
+// not that it matches what a given plugin build sends.
+
+use rbr_telemetry::*;
+
+const DOCUMENTATION: &str = include_str!("../../documentation/telemetry_documentation.txt");
+const DOCUMENTED_PACKET: &[u8] = include_bytes!("fixtures/documented_packet.bin");
+
+struct DocumentedField {
+    name: String,
+    kind: FieldKind,
+    value: f64,
+}
+
+// Lines look like: F32, "m/s", "LF.deflectionVelocity"   // 1.75195e-06
+fn documented_fields() -> Vec<DocumentedField> {
+    DOCUMENTATION
+        .lines()
+        .filter_map(|line| {
+            let (declaration, value) = line.split_once("//")?;
+            let mut parts = declaration.split(',').map(|p| p.trim().trim_matches('"'));
+            let kind = match parts.next()? {
+                "F32" => FieldKind::F32,
+                "I32" => FieldKind::I32,
+                "I16" => FieldKind::I16,
+                _ => return None,
+            };
+            let name = parts.nth(1)?.to_string();
+            let value = value.split_whitespace().next()?.trim_end_matches('.').parse().ok()?;
+            Some(DocumentedField { name, kind, value })
+        })
+        .collect()
+}
+
+#[test]
+fn documented_layout_offsets_match_documentation() {
+    let documented = documented_fields();
+    let fields: Vec<&Field> = DOCUMENTED_LAYOUT.fields().collect();
+    assert_eq!(documented.len(), 177);
+    assert_eq!(fields.len(), documented.len());
+    let mut offset = 0;
+    for (field, doc) in fields.iter().zip(&documented) {
+        assert_eq!(field.name, doc.name, "field order at offset {offset}");
+        assert_eq!(field.kind, doc.kind, "{} type", field.name);
+        assert_eq!(field.offset, offset, "{} offset", field.name);
+        offset += field.kind.size();
+    }
+    assert_eq!(offset, DOCUMENTED_LAYOUT.size);
+    assert_eq!(DOCUMENTED_PACKET.len(), DOCUMENTED_LAYOUT.size);
+}
+
+#[test]
+fn documented_fields_decode_from_documented_packet() {
+    let telemetry = DOCUMENTED_LAYOUT.decode(DOCUMENTED_PACKET).unwrap();
+    for (field, doc) in DOCUMENTED_LAYOUT.fields().zip(documented_fields()) {
+        let value = (field.get)(&telemetry);
+        match field.kind {
+            FieldKind::F32 => assert_eq!(
+                value as f32, doc.value as f32,
+                "{} at offset {}", field.name, field.offset
+            ),
+            _ => assert_eq!(value, doc.value, "{} at offset {}", field.name, field.offset),
+        }
+    }
+}
+
+#[test]
+fn documented_packet_spot_checks() {
+    let telemetry = DOCUMENTED_LAYOUT.decode(DOCUMENTED_PACKET).unwrap();
+    assert_eq!(telemetry.stage.progress, 8.29773);
+    assert_eq!(telemetry.stage.distance_to_end, 5220.61);
+    assert_eq!(telemetry.control.gear, 1);
+    // first field after the 16 bit gear
+    assert_eq!(telemetry.control.footbrake_pressure, 0.0);
+    assert_eq!(telemetry.control.handbrake_pressure, 7.8e6);
+    assert_eq!(telemetry.car.engine.rpm, 1212.53);
+    assert_eq!(telemetry.car.yaw, 1.69717);
+    assert_eq!(telemetry.car.suspension_lf.spring_deflection, 0.231372);
+    assert_eq!(telemetry.car.suspension_lf.damper.piston_velocity, 1.75195e-6);
+    assert_eq!(telemetry.car.suspension_lf.wheel.brake_disk.temperature, 672377536.0 / 2097152.0);
+    assert_eq!(telemetry.car.suspension_rf.wheel.tire.segment1.temperature, 319.496);
+    assert_eq!(telemetry.car.suspension_lb.rollbar_force, 20.8307);
+    assert_eq!(telemetry.car.suspension_rb.strut_force, -3026.97);
+    assert_eq!(telemetry.car.suspension_rb.wheel.tire.carcass_temperature, 343.104);
+}
+
+#[test]
+fn documented_brake_disks_decode_to_kelvin() {
+    let telemetry = DOCUMENTED_LAYOUT.decode(DOCUMENTED_PACKET).unwrap();
+    for suspension in telemetry.car.suspensions() {
+        let disk = &suspension.wheel.brake_disk;
+        let segment = suspension.wheel.tire.segment1.temperature;
+        // a parked car: the disks are about as warm as the tires
+        for kelvin in [disk.temperature, disk.layer_temperature] {
+            assert!((kelvin - segment).abs() < 5.0, "{kelvin} K next to {segment} K tires");
+        }
+        assert_eq!(disk.wear, 0.0);
+    }
+    let formatted = Telemetry::decode(DOCUMENTED_PACKET).unwrap();
+    let celsius = formatted.car.suspension_rb.wheel.brake_disk.temperature;
+    assert!((40.0..55.0).contains(&celsius), "{celsius} °C");
+}
+
+#[test]
+fn telemetry_data_layout_is_contiguous() {
+    let mut offset = 0;
+    for field in TELEMETRY_DATA_LAYOUT.fields() {
+        assert_eq!(field.offset, offset, "{} offset", field.name);
+        assert_eq!(field.kind.size(), 4, "{} size", field.name);
+        offset += field.kind.size();
+    }
+    assert_eq!(offset, TELEMETRY_DATA_LAYOUT.size);
+    assert_eq!(TELEMETRY_DATA_LAYOUT.size, 664);
+}
+
+#[test]
+fn every_field_has_its_own_bytes() {
+    for layout in [&TELEMETRY_DATA_LAYOUT, &DOCUMENTED_LAYOUT] {
+        for (i, field) in layout.fields().enumerate() {
+            let marker = i as f64 + 1.0;
+            let mut telemetry = Telemetry::default();
+            (field.set)(&mut telemetry, marker);
+            if (field.get)(&telemetry) == 0.0 {
+                // not decoded
+                continue;
+            }
+            let data = layout.encode(&telemetry);
+            let decoded = layout.decode(&data).unwrap();
+            for other in layout.fields() {
+                let expected = if other.name == field.name { marker } else { 0.0 };
+                assert_eq!((other.get)(&decoded), expected, "{} set {}", field.name, other.name);
+            }
+        }
+    }
+}
+
+#[test]
+fn decode_picks_layout_by_size() {
+    let telemetry = Telemetry::decode(DOCUMENTED_PACKET).unwrap();
+    // formatted: gear 1 is neutral
+    assert_eq!(telemetry.control.gear, 0);
+
+    let mut raw = Telemetry::default();
+    raw.control.throttle = 0.5;
+    raw.control.gear = 3;
+    let telemetry = Telemetry::decode(&TELEMETRY_DATA_LAYOUT.encode(&raw)).unwrap();
+    assert_eq!(telemetry.control.throttle, 50.0);
+    assert_eq!(telemetry.control.gear, 2);
+
+    assert_eq!(
+        Telemetry::decode(&DOCUMENTED_PACKET[..700]).err(),
+        Some(DecodeError::UnknownLayout { size: 700 })
+    );
+
+    let (_, layout) = Telemetry::decode_layout(DOCUMENTED_PACKET).unwrap();
+    assert_eq!(layout.version, DOCUMENTED_LAYOUT.version);
+    assert_eq!(layout_by_version(1).unwrap().name, TELEMETRY_DATA_LAYOUT.name);
+    assert_eq!(MAX_PACKET_SIZE, 706);
+}
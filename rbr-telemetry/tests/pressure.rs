@@ -0,0 +1,63 @@
+mod common;
+
+use common::*;
+use rbr_telemetry::*;
+
+#[test]
+fn cold_pressures_come_from_the_start() {
+    let mut pressures = StagePressures::default();
+    for i in 0..COLD_PRESSURE_FRAMES {
+        assert_eq!(pressures.cold(), None);
+        let warming = frame(i as f32 * 0.02)
+            .stage(1)
+            .tire_pressure(0, 180_000.0 + i as f32 * 100.0)
+            .tire_pressure(3, 200_000.0);
+        pressures.add(&warming);
+    }
+    let cold = pressures.cold().unwrap();
+    let average = 180_000.0 + (COLD_PRESSURE_FRAMES - 1) as f32 * 50.0;
+    assert_eq!(cold, [average, 0.0, 0.0, 200_000.0]);
+
+    // later, warmer frames don't move them
+    pressures.add(&frame(60.0).stage(1).progress(1500.0).tire_pressure(0, 220_000.0));
+    assert_eq!(pressures.cold().unwrap()[0], average);
+}
+
+#[test]
+fn joining_mid_stage_gives_no_cold_pressures() {
+    let mut pressures = StagePressures::default();
+    for i in 0..COLD_PRESSURE_FRAMES * 2 {
+        pressures.add(&frame(90.0 + i as f32 * 0.02).stage(1).progress(2000.0 + i as f32).tire_pressure(0, 220_000.0));
+    }
+    assert_eq!(pressures.cold(), None);
+}
+
+#[test]
+fn a_short_start_still_gives_cold_pressures() {
+    let mut pressures = StagePressures::default();
+    // few frames near the start, e.g. a slow link
+    for i in 0..5 {
+        pressures.add(&frame(i as f32).stage(1).progress(i as f32 * 5.0).tire_pressure(0, 180_000.0));
+    }
+    assert_eq!(pressures.cold(), None);
+    pressures.add(&frame(COLD_PRESSURE_TIME + 1.0).stage(1).progress(COLD_PRESSURE_DISTANCE + 1.0).tire_pressure(0, 200_000.0));
+    assert_eq!(pressures.cold().unwrap()[0], 180_000.0);
+}
+
+#[test]
+fn restart_collects_again() {
+    let mut pressures = StagePressures::default();
+    for i in 0..COLD_PRESSURE_FRAMES {
+        pressures.add(&frame(i as f32 * 0.02).stage(1).tire_pressure(0, 180_000.0));
+    }
+    pressures.add(&frame(0.0).stage(1).tire_pressure(0, 190_000.0));
+    assert_eq!(pressures.cold(), None);
+    for i in 1..COLD_PRESSURE_FRAMES {
+        pressures.add(&frame(i as f32 * 0.02).stage(1).tire_pressure(0, 190_000.0));
+    }
+    assert_eq!(pressures.cold().unwrap()[0], 190_000.0);
+
+    // so does another stage
+    pressures.add(&frame(50.0).stage(2).tire_pressure(0, 170_000.0));
+    assert_eq!(pressures.cold(), None);
+}
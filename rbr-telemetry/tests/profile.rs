@@ -0,0 +1,88 @@
+mod common;
+
+use common::*;
+use rbr_telemetry::*;
+
+// runs given as (race time, progress) frames, back to back in one session
+fn runs(runs: &[&[(f32, f32)]]) -> Session {
+    let frames = runs.iter()
+        .flat_map(|run| run.iter())
+        .map(|&(time, progress)| frame(time).stage(4).progress(progress).build());
+    session(4, 0, frames)
+}
+
+#[test]
+fn interpolates_time_between_frames() {
+    let mut run = RunProfile::new(&[]);
+    run.add(&frame(1.0).progress(10.0));
+    run.add(&frame(2.0).progress(30.0));
+    assert_eq!(run.time_at(20.0), Some(1.5));
+    assert_eq!(run.time_at(10.0), Some(1.0));
+    assert_eq!(run.time_at(30.0), Some(2.0));
+    assert_eq!(run.time_at(5.0), None);
+    assert_eq!(run.time_at(31.0), None);
+    assert_eq!(run.delta(20.0, 1.75), Some(0.25));
+}
+
+#[test]
+fn keeps_only_frames_that_move_forward() {
+    let mut run = RunProfile::new(&[Channel::THROTTLE]);
+    assert!(!run.add(&frame(0.0).stage(4)), "before the start");
+    assert!(run.add(&frame(1.0).stage(4).progress(10.0).throttle(0.1)));
+    assert!(!run.add(&frame(1.5).stage(4).progress(10.0)), "standing still");
+    assert!(!run.add(&frame(2.0).stage(4).progress(8.0)), "rolling back");
+    assert!(run.add(&frame(3.0).stage(4).progress(12.0).throttle(0.12)));
+    assert_eq!(run.distances(), &[10.0, 12.0]);
+    assert_eq!(run.values(Channel::THROTTLE), Some(&[0.1, 0.12][..]));
+    assert_eq!(run.values(Channel::BRAKE), None);
+    assert_eq!(run.stage_index, 4);
+}
+
+#[test]
+fn picks_the_furthest_then_quickest_run_of_a_session() {
+    let short: &[(f32, f32)] = &[(1.0, 10.0), (2.0, 20.0)];
+    let slow: &[(f32, f32)] = &[(1.0, 10.0), (2.0, 20.0), (4.0, 30.0)];
+    let quick: &[(f32, f32)] = &[(1.0, 10.0), (2.0, 20.0), (3.0, 30.0)];
+    let run = RunProfile::from_session(&runs(&[slow, quick, short]), &[], None).unwrap();
+    assert_eq!(run.end_time(), 3.0);
+    assert_eq!(run.end_distance(), 30.0);
+    assert!(RunProfile::from_session(&runs(&[]), &[], None).is_none());
+}
+
+#[test]
+fn another_stage_or_car_starts_a_new_run() {
+    // race time keeps counting up across the changes, e.g. a stage restarted from the menu
+    let frames = [
+        frame(1.0).stage(4).progress(10.0),
+        frame(2.0).stage(4).progress(20.0),
+        frame(3.0).stage(4).progress(30.0),
+        frame(4.0).stage(7).progress(10.0),
+        frame(5.0).stage(7).progress(20.0),
+        frame(6.0).stage(7).car(2).progress(30.0),
+    ];
+    let session = session(4, 0, frames.into_iter().map(Frame::build));
+    let runs = RunProfile::runs(&session, &[]);
+    let ids: Vec<(i32, i32)> = runs.iter().map(|run| (run.stage_index, run.car_index)).collect();
+    assert_eq!(ids, [(4, 0), (7, 0), (7, 2)]);
+    assert_eq!(runs[1].distances(), &[10.0, 20.0]);
+}
+
+#[test]
+fn the_caller_picks_the_stage_and_car() {
+    let frames = [
+        frame(1.0).stage(4).progress(10.0),
+        frame(2.0).stage(4).progress(20.0),
+        frame(1.0).stage(4).car(2).progress(10.0),
+        frame(2.0).stage(4).car(2).progress(30.0),
+        frame(1.0).stage(7).progress(10.0),
+        frame(2.0).stage(7).progress(500.0),
+    ];
+    let session = session(4, 0, frames.into_iter().map(Frame::build));
+    let run = RunProfile::from_session(&session, &[], Some((4, 0))).unwrap();
+    assert_eq!((run.stage_index, run.car_index), (4, 0));
+    assert_eq!(run.end_distance(), 20.0);
+    let run = RunProfile::from_session(&session, &[], Some((4, 2))).unwrap();
+    assert_eq!((run.stage_index, run.car_index), (4, 2));
+    assert_eq!(RunProfile::from_session(&session, &[], None).unwrap().stage_index, 7);
+    assert!(RunProfile::from_session(&session, &[], Some((7, 2))).is_none());
+}
@@ -0,0 +1,71 @@
+// Packet validation: values the game can't send are rejected before they reach the views.
+
+use rbr_telemetry::*;
+
+fn packet(raw: &Telemetry) -> Vec<u8> {
+    TELEMETRY_DATA_LAYOUT.encode(raw)
+}
+
+#[test]
+fn default_packet_is_valid() {
+    assert!(Telemetry::default().validate().is_ok());
+    assert!(Telemetry::decode(&packet(&Telemetry::default())).is_ok());
+}
+
+#[test]
+fn non_finite_values_are_rejected() {
+    let mut raw = Telemetry::default();
+    raw.car.speed = f32::NAN;
+    assert_eq!(raw.validate(), Err(DecodeError::NonFinite { field: "car.speed" }));
+
+    let mut raw = Telemetry::default();
+    raw.car.suspension_rb.wheel.tire.pressure = f32::INFINITY;
+    assert!(matches!(raw.validate(), Err(DecodeError::NonFinite { .. })));
+
+    let mut raw = Telemetry::default();
+    raw.control.throttle = f32::NAN;
+    assert_eq!(
+        Telemetry::decode(&packet(&raw)).err(),
+        Some(DecodeError::NonFinite { field: "control.throttle" })
+    );
+}
+
+#[test]
+fn out_of_range_values_are_rejected() {
+    let mut raw = Telemetry::default();
+    raw.control.throttle = 1.5;
+    assert_eq!(raw.validate(), Err(DecodeError::OutOfRange { field: "control.throttle", value: 1.5 }));
+
+    let mut raw = Telemetry::default();
+    raw.control.steering = -1.01;
+    assert!(matches!(raw.validate(), Err(DecodeError::OutOfRange { field: "control.steering", .. })));
+
+    let mut raw = Telemetry::default();
+    raw.stage.race_time = -1.0;
+    assert!(matches!(raw.validate(), Err(DecodeError::OutOfRange { field: "stage.race_time", .. })));
+
+    let mut raw = Telemetry::default();
+    raw.control.gear = 8;
+    assert_eq!(raw.validate(), Err(DecodeError::OutOfRange { field: "control.gear", value: 8.0 }));
+
+    let mut raw = Telemetry::default();
+    raw.stage.index = -1;
+    assert!(matches!(Telemetry::decode(&packet(&raw)), Err(DecodeError::OutOfRange { field: "stage.index", .. })));
+}
+
+#[test]
+fn rejected_packets_are_counted_by_kind() {
+    let mut rejected = RejectedPackets::default();
+    let mut nan = Telemetry::default();
+    nan.car.yaw = f32::NAN;
+    let mut out_of_range = Telemetry::default();
+    out_of_range.control.brake = 2.0;
+    for data in [packet(&nan), packet(&out_of_range), vec![0; 10]] {
+        rejected.add(&Telemetry::decode(&data).err().unwrap());
+    }
+    assert_eq!(rejected.non_finite, 1);
+    assert_eq!(rejected.out_of_range, 1);
+    assert_eq!(rejected.unknown_layout, 1);
+    assert_eq!(rejected.total, 3);
+    assert_eq!(rejected.last_error, Some(DecodeError::UnknownLayout { size: 10 }));
+}
@@ -0,0 +1,100 @@
+// Re-sends the raw datagrams of a recorded session over UDP with their original timing,
+// standing in for the game on the network.
+//
+// usage: rbr-replay <session file> [--address 127.0.0.1] [--port 6776] [--speed 1] [--loop]
+
+use std::env;
+use std::net::UdpSocket;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rbr_telemetry::Session;
+
+const USAGE: &str = "usage: rbr-replay <session file> [--address 127.0.0.1] [--port 6776] [--speed 1] [--loop]";
+
+struct Options {
+    path: PathBuf,
+    address: String,
+    port: u16,
+    speed: f64,
+    looping: bool,
+}
+impl Options {
+    fn parse() -> Result<Options, String> {
+        let mut path = None;
+        let mut options = Options {
+            path: PathBuf::new(),
+            address: String::from("127.0.0.1"),
+            port: 6776,
+            speed: 1.0,
+            looping: false,
+        };
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "--address" => options.address = value()?,
+                "--port" => options.port = value()?.parse().map_err(|_| "invalid port")?,
+                "--speed" => options.speed = value()?.parse().map_err(|_| "invalid speed")?,
+                "--loop" => options.looping = true,
+                _ if !arg.starts_with("--") && path.is_none() => path = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        if options.speed.is_nan() || options.speed <= 0.0 {
+            return Err("speed must be positive".to_string());
+        }
+        options.path = path.ok_or("missing session file")?;
+        Ok(options)
+    }
+}
+
+// Sends every frame once, sleeping so frame N leaves `time_us / speed` after the first.
+fn send_session(session: &Session, socket: &UdpSocket, target: &str, speed: f64) {
+    let start = Instant::now();
+    for frame in &session.frames {
+        let due = Duration::from_secs_f64(frame.time_us as f64 / 1_000_000.0 / speed);
+        if let Some(wait) = due.checked_sub(start.elapsed()) {
+            thread::sleep(wait);
+        }
+        if let Err(e) = socket.send_to(&frame.data, target) {
+            eprintln!("send failed: {e}");
+        }
+    }
+}
+
+fn main() {
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            process::exit(2);
+        },
+    };
+    let session = match Session::load(&options.path) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("{}: {e}", options.path.display());
+            process::exit(1);
+        },
+    };
+    let socket = UdpSocket::bind("0.0.0.0:0").expect("Failed to bind socket");
+    let target = format!("{}:{}", options.address, options.port);
+    println!(
+        "replaying {} frames ({:.1}s, stage {}, car {}) to {target} at {}x",
+        session.frames.len(),
+        session.duration(),
+        session.header.stage_index,
+        session.header.car_index,
+        options.speed
+    );
+    loop {
+        send_session(&session, &socket, &target, options.speed);
+        if !options.looping {
+            break;
+        }
+    }
+}
@@ -0,0 +1,393 @@
+// Sends synthetic NGP telemetry packets over UDP, so the GUI can be worked on without the game.
+//
+// usage: rbr-sim [--address 127.0.0.1] [--port 6776] [--rate 60] [--length 5000] [--stage 1] [--car 0] [--documented]
+
+use std::env;
+use std::net::UdpSocket;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rbr_telemetry::*;
+
+const KELVIN: f32 = 273.15;
+const AMBIENT_TEMP: f32 = 20.0;
+const GEAR_RATIOS: [f32; 6] = [3.6, 2.4, 1.8, 1.4, 1.15, 0.95];
+const IDLE_RPM: f32 = 1000.0;
+const SHIFT_UP_RPM: f32 = 7200.0;
+const SHIFT_DOWN_RPM: f32 = 3800.0;
+const SHIFT_TIME: f32 = 0.25; // seconds with the clutch in
+const TIRE_PRESSURE: f32 = 220000.0; // Pa
+
+struct Options {
+    address: String,
+    port: u16,
+    rate: f32,
+    length: f32,
+    stage: i32,
+    car: i32,
+    layout: &'static Layout,
+}
+impl Options {
+    fn parse() -> Result<Options, String> {
+        let mut options = Options {
+            address: String::from("127.0.0.1"),
+            port: 6776,
+            rate: 60.0,
+            length: 5000.0,
+            stage: 1,
+            car: 0,
+            layout: &TELEMETRY_DATA_LAYOUT,
+        };
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "--address" => options.address = value()?,
+                "--port" => options.port = value()?.parse().map_err(|_| "invalid port")?,
+                "--rate" => options.rate = value()?.parse().map_err(|_| "invalid rate")?,
+                "--length" => options.length = value()?.parse().map_err(|_| "invalid length")?,
+                "--stage" => options.stage = value()?.parse().map_err(|_| "invalid stage")?,
+                "--car" => options.car = value()?.parse().map_err(|_| "invalid car")?,
+                // send the layout from documentation/telemetry_documentation.txt instead
+                "--documented" => options.layout = &DOCUMENTED_LAYOUT,
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        if options.rate.is_nan() || options.rate <= 0.0 || options.length.is_nan() || options.length <= 0.0 {
+            return Err("rate and length must be positive".to_string());
+        }
+        Ok(options)
+    }
+}
+
+// xorshift, good enough for road noise
+struct Noise(u32);
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+struct Corner {
+    deflection: f32,
+    deflection_velocity: f32,
+    tire_temp: f32,
+    tread_temp: f32,
+    carcass_temp: f32,
+    segment_temps: [f32; 8],
+    segment_wear: [f32; 8],
+    rotation: f32,
+    brake_temp: f32,
+    brake_layer_temp: f32,
+    brake_wear: f32,
+}
+impl Corner {
+    fn new() -> Self {
+        Corner {
+            deflection: 0.23,
+            deflection_velocity: 0.0,
+            tire_temp: AMBIENT_TEMP,
+            tread_temp: AMBIENT_TEMP,
+            carcass_temp: AMBIENT_TEMP,
+            segment_temps: [AMBIENT_TEMP; 8],
+            segment_wear: [0.0; 8],
+            rotation: 0.0,
+            brake_temp: AMBIENT_TEMP,
+            brake_layer_temp: AMBIENT_TEMP,
+            brake_wear: 0.0,
+        }
+    }
+
+    fn update(&mut self, dt: f32, speed: f32, accel: f32, brake: f32, load: f32, noise: &mut Noise) {
+        // spring-damper following a bumpy road
+        let target = 0.23 + load * 0.02 + noise.next() * 0.01 * (speed / 30.0).min(1.0);
+        let spring = (target - self.deflection) * 400.0 - self.deflection_velocity * 25.0;
+        self.deflection_velocity += spring * dt;
+        self.deflection = (self.deflection + self.deflection_velocity * dt).clamp(0.05, 0.4);
+
+        let heating = speed * 0.02 + accel.abs() * 0.4 + load.abs() * 2.0;
+        let cooling = (self.tread_temp - AMBIENT_TEMP) * 0.02;
+        self.tread_temp += (heating - cooling) * dt;
+        self.carcass_temp += (self.tread_temp - self.carcass_temp) * 0.05 * dt;
+        self.tire_temp = (self.tread_temp + self.carcass_temp) * 0.5;
+
+        // 0.3m tire radius
+        self.rotation = (self.rotation + speed / 0.3 * dt).rem_euclid(std::f32::consts::TAU);
+        let current = self.current_segment();
+        for (i, temperature) in self.segment_temps.iter_mut().enumerate() {
+            let contact = if i == current { 3.0 } else { 0.0 };
+            *temperature += (self.tread_temp + contact - *temperature) * 0.5 * dt;
+        }
+        self.segment_wear[current] += speed * dt * 1e-6;
+
+        let brake_heat = brake * speed * 4.0;
+        let brake_cooling = (self.brake_temp - AMBIENT_TEMP) * (0.01 + speed * 0.002);
+        self.brake_temp += (brake_heat - brake_cooling) * dt;
+        self.brake_layer_temp += (self.brake_temp - self.brake_layer_temp) * 0.2 * dt;
+        self.brake_wear += brake * speed * dt * 1e-6;
+    }
+
+    fn current_segment(&self) -> usize {
+        ((self.rotation / std::f32::consts::TAU) * 8.0) as usize % 8
+    }
+
+    fn suspension(&self, load: f32) -> Suspension {
+        let spring_force = 3500.0 + load * 800.0 + (self.deflection - 0.23) * 30000.0;
+        let damper_force = self.deflection_velocity * 2500.0;
+        let tire = Tire {
+            pressure: TIRE_PRESSURE + (self.tire_temp - AMBIENT_TEMP) * 300.0,
+            temperature: self.tire_temp + KELVIN,
+            carcass_temperature: self.carcass_temp + KELVIN,
+            tread_temperature: self.tread_temp + KELVIN,
+            current_segment: self.current_segment() as u32,
+            segment1: self.segment(0),
+            segment2: self.segment(1),
+            segment3: self.segment(2),
+            segment4: self.segment(3),
+            segment5: self.segment(4),
+            segment6: self.segment(5),
+            segment7: self.segment(6),
+            segment8: self.segment(7),
+        };
+        Suspension {
+            spring_deflection: self.deflection,
+            rollbar_force: load * 20.0,
+            spring_force,
+            damper_force,
+            strut_force: -(spring_force + damper_force),
+            helper_spring_is_active: (self.deflection > 0.3) as i32,
+            damper: Damper {
+                damage: 0.0,
+                piston_velocity: self.deflection_velocity,
+            },
+            wheel: Wheel {
+                brake_disk: BrakeDisk {
+                    layer_temperature: self.brake_layer_temp + KELVIN,
+                    temperature: self.brake_temp + KELVIN,
+                    wear: self.brake_wear,
+                },
+                tire,
+            },
+        }
+    }
+
+    fn segment(&self, i: usize) -> TireSegment {
+        TireSegment {
+            temperature: self.segment_temps[i] + KELVIN,
+            wear: self.segment_wear[i],
+        }
+    }
+}
+
+struct Car {
+    distance: f32,
+    time: f32,
+    speed: f32, // m/s
+    gear: usize,
+    shifting: f32,
+    throttle: f32,
+    brake: f32,
+    steering: f32,
+    heading: f32,
+    x: f32,
+    y: f32,
+    accel: f32,
+    engine_temp: f32,
+    // weight transfer from the last step, per corner in LF, RF, LB, RB order
+    lateral: f32,
+    longitudinal: f32,
+    loads: [f32; 4],
+    corners: [Corner; 4],
+    steps: u32,
+}
+impl Car {
+    fn new() -> Self {
+        Car {
+            distance: 0.0,
+            time: 0.0,
+            speed: 0.0,
+            gear: 0,
+            shifting: 0.0,
+            throttle: 0.0,
+            brake: 0.0,
+            steering: 0.0,
+            heading: 0.0,
+            x: 0.0,
+            y: 0.0,
+            accel: 0.0,
+            engine_temp: 85.0,
+            lateral: 0.0,
+            longitudinal: 0.0,
+            loads: [0.0; 4],
+            corners: [Corner::new(), Corner::new(), Corner::new(), Corner::new()],
+            steps: 0,
+        }
+    }
+
+    fn rpm(&self) -> f32 {
+        (self.speed * GEAR_RATIOS[self.gear] * 110.0).max(IDLE_RPM)
+    }
+
+    // A stage made of straights and corners of varying tightness.
+    fn target_speed(distance: f32) -> f32 {
+        let curve = (distance / 180.0).sin() * 0.6 + (distance / 67.0).sin() * 0.4;
+        38.0 - curve.abs() * 22.0
+    }
+
+    fn step(&mut self, dt: f32, noise: &mut Noise) {
+        // look ahead so braking starts before the corner
+        let target = Car::target_speed(self.distance + self.speed * 2.0);
+        if self.speed < target - 1.0 {
+            self.throttle = (self.throttle + dt * 4.0).min(1.0);
+            self.brake = 0.0;
+        } else if self.speed > target + 2.0 {
+            self.throttle = 0.0;
+            self.brake = ((self.speed - target) / 8.0).clamp(0.2, 1.0);
+        } else {
+            self.throttle = 0.4;
+            self.brake = 0.0;
+        }
+
+        let rpm = self.rpm();
+        if self.shifting > 0.0 {
+            self.shifting -= dt;
+        } else if rpm > SHIFT_UP_RPM && self.gear < GEAR_RATIOS.len() - 1 {
+            self.gear += 1;
+            self.shifting = SHIFT_TIME;
+        } else if rpm < SHIFT_DOWN_RPM && self.gear > 0 {
+            self.gear -= 1;
+            self.shifting = SHIFT_TIME;
+        }
+        let drive = if self.shifting > 0.0 { 0.0 } else { self.throttle * GEAR_RATIOS[self.gear] * 2.2 };
+        self.accel = drive - self.brake * 11.0 - self.speed * self.speed * 0.0015 - 0.2;
+        self.speed = (self.speed + self.accel * dt).max(0.0);
+
+        self.steering = ((self.distance / 180.0).cos() * 0.6 + (self.distance / 67.0).cos() * 0.4)
+            .clamp(-1.0, 1.0);
+        self.heading += self.steering * self.speed * dt * 0.02;
+        self.x += self.heading.cos() * self.speed * dt;
+        self.y += self.heading.sin() * self.speed * dt;
+        self.distance += self.speed * dt;
+        self.time += dt;
+        self.steps += 1;
+
+        self.lateral = self.steering * self.speed / 30.0;
+        self.longitudinal = -self.accel / 10.0;
+        let (lateral, longitudinal) = (self.lateral, self.longitudinal);
+        self.loads = [
+            longitudinal + lateral,
+            longitudinal - lateral,
+            -longitudinal + lateral,
+            -longitudinal - lateral,
+        ];
+        for (i, corner) in self.corners.iter_mut().enumerate() {
+            // front brakes do most of the work
+            let brake = if i < 2 { self.brake } else { self.brake * 0.6 };
+            corner.update(dt, self.speed, self.accel, brake, self.loads[i], noise);
+        }
+        self.engine_temp += ((85.0 + rpm / 1000.0 * 2.0) - self.engine_temp) * 0.01 * dt;
+    }
+
+    fn telemetry(&self, options: &Options) -> Telemetry {
+        let (lateral, longitudinal, loads) = (self.lateral, self.longitudinal, self.loads);
+        Telemetry {
+            total_steps: self.steps,
+            stage: Stage {
+                index: options.stage,
+                progress: self.distance,
+                race_time: self.time,
+                drive_line_location: self.distance,
+                travel_distance: self.distance,
+                distance_to_end: (options.length - self.distance).max(0.0),
+            },
+            control: Control {
+                steering: self.steering,
+                throttle: self.throttle,
+                brake: self.brake,
+                handbrake: 0.0,
+                clutch: if self.shifting > 0.0 { 1.0 } else { 0.0 },
+                // the plugin sends reverse as 0 and neutral as 1
+                gear: self.gear as i32 + 2,
+                footbrake_pressure: self.brake * 8.0e6,
+                handbrake_pressure: 0.0,
+            },
+            car: rbr_telemetry::Car {
+                index: options.car,
+                speed: self.speed * 3.6,
+                position_x: self.x,
+                position_y: self.y,
+                position_z: 0.0,
+                roll: lateral * 0.02,
+                pitch: longitudinal * 0.02,
+                yaw: self.heading,
+                velocities: Motion {
+                    surge: self.speed,
+                    ..Default::default()
+                },
+                accelerations: Motion {
+                    surge: self.accel,
+                    sway: lateral * 9.81,
+                    ..Default::default()
+                },
+                linear_velocity: Vector3 {
+                    x: self.speed,
+                    ..Default::default()
+                },
+                linear_acceleration: Vector3 {
+                    x: self.accel,
+                    y: lateral * 9.81,
+                    ..Default::default()
+                },
+                engine: Engine {
+                    rpm: self.rpm(),
+                    radiator_coolant_temperature: self.engine_temp - 2.0 + KELVIN,
+                    engine_coolant_temperature: self.engine_temp + KELVIN,
+                    engine_temperature: self.engine_temp + 5.0 + KELVIN,
+                },
+                suspension_lf: self.corners[0].suspension(loads[0]),
+                suspension_rf: self.corners[1].suspension(loads[1]),
+                suspension_lb: self.corners[2].suspension(loads[2]),
+                suspension_rb: self.corners[3].suspension(loads[3]),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+fn main() {
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("usage: rbr-sim [--address 127.0.0.1] [--port 6776] [--rate 60] [--length 5000] [--stage 1] [--car 0] [--documented]");
+            process::exit(2);
+        },
+    };
+    let socket = UdpSocket::bind("0.0.0.0:0").expect("Failed to bind socket");
+    let target = format!("{}:{}", options.address, options.port);
+    println!("sending {} telemetry to {target} at {} Hz", options.layout.name, options.rate);
+
+    let dt = 1.0 / options.rate;
+    let mut noise = Noise(0x9e37_79b9);
+    let mut car = Car::new();
+    let mut next = Instant::now();
+    loop {
+        car.step(dt, &mut noise);
+        if car.distance >= options.length {
+            println!("stage finished in {:.2}s, restarting", car.time);
+            car = Car::new();
+        }
+        let packet = options.layout.encode(&car.telemetry(&options));
+        if let Err(e) = socket.send_to(&packet, &target) {
+            eprintln!("send failed: {e}");
+        }
+        next += Duration::from_secs_f32(dt);
+        if let Some(wait) = next.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+    }
+}
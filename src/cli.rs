@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+use std::env;
+use std::path::PathBuf;
+
+// resources.rs
+use crate::resources::DisplayState;
+// settings.rs
+use crate::settings::Settings;
+
+pub const USAGE: &str = "usage: RBR-GUI [--port 6776] [--address 127.0.0.1] [--view main|pedals|tires|suspension|dampers|engine|compare] \
+[--always-on-top true|false] [--record <file>] [--replay <file>] [--reference <file>]";
+
+// Launch options, everything left out keeps the value from the settings file.
+// They override the settings for this run only and are never saved.
+#[derive(Resource, Debug, Default, PartialEq)]
+pub struct Options {
+    pub port: Option<u16>,
+    pub address: Option<String>,
+    pub view: Option<DisplayState>,
+    pub always_on_top: Option<bool>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub reference: Option<PathBuf>,
+}
+impl Options {
+    pub fn parse() -> Result<Options, String> {
+        Options::parse_from(env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "--port" => options.port = Some(value()?.parse().map_err(|_| "invalid port")?),
+                "--address" => {
+                    let address = value()?;
+                    address.parse::<std::net::IpAddr>().map_err(|_| "invalid address")?;
+                    options.address = Some(address);
+                },
+                "--view" => options.view = Some(parse_view(&value()?)?),
+                "--always-on-top" => {
+                    options.always_on_top = Some(value()?.parse().map_err(|_| "--always-on-top takes true or false")?)
+                },
+                "--record" => options.record = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--reference" => options.reference = Some(PathBuf::from(value()?)),
+                "--help" | "-h" => return Err(String::new()),
+                _ => return Err(format!("unknown argument {arg}")),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(port) = self.port {
+            settings.port = port.to_string();
+        }
+        if let Some(address) = &self.address {
+            settings.ip = address.clone();
+        }
+        if let Some(view) = &self.view {
+            settings.view = view.clone();
+        }
+        if let Some(always_on_top) = self.always_on_top {
+            settings.window.always_on_top = always_on_top;
+        }
+    }
+
+    // What to write to the settings file: `current` with every field still at its
+    // launch value put back to what the file had. A field changed in the menus since
+    // launch is the user's choice and is saved.
+    pub fn persisted(&self, current: &Settings, saved: &Settings) -> Settings {
+        let mut settings = current.clone();
+        if self.port.is_some_and(|port| current.port == port.to_string()) {
+            settings.port = saved.port.clone();
+        }
+        if self.address.as_ref().is_some_and(|address| &current.ip == address) {
+            settings.ip = saved.ip.clone();
+        }
+        if self.view.as_ref().is_some_and(|view| &current.view == view) {
+            settings.view = saved.view.clone();
+        }
+        if self.always_on_top == Some(current.window.always_on_top) {
+            settings.window.always_on_top = saved.window.always_on_top;
+        }
+        settings
+    }
+}
+
+fn parse_view(view: &str) -> Result<DisplayState, String> {
+    match view.to_lowercase().as_str() {
+        "main" => Ok(DisplayState::Main),
+        "pedals" => Ok(DisplayState::Pedals),
+        "tires" => Ok(DisplayState::Tires),
+        "suspension" => Ok(DisplayState::Suspension),
+        "dampers" => Ok(DisplayState::Dampers),
+        "engine" => Ok(DisplayState::Engine),
+        "compare" => Ok(DisplayState::Compare),
+        _ => Err(format!("unknown view {view}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse_from(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_every_option() {
+        let options = parse(&[
+            "--port", "6777", "--address", "0.0.0.0", "--view", "Pedals", "--always-on-top", "false",
+            "--record", "run.rbrs", "--replay", "old.rbrs", "--reference", "best.rbrs",
+        ]).unwrap();
+        assert_eq!(options, Options {
+            port: Some(6777),
+            address: Some("0.0.0.0".to_string()),
+            view: Some(DisplayState::Pedals),
+            always_on_top: Some(false),
+            record: Some(PathBuf::from("run.rbrs")),
+            replay: Some(PathBuf::from("old.rbrs")),
+            reference: Some(PathBuf::from("best.rbrs")),
+        });
+        assert_eq!(parse(&[]).unwrap(), Options::default());
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(parse(&["--port", "70000"]).err(), Some("invalid port".to_string()));
+        assert_eq!(parse(&["--address", "localhost"]).err(), Some("invalid address".to_string()));
+        assert_eq!(parse(&["--view", "map"]).err(), Some("unknown view map".to_string()));
+        assert_eq!(parse(&["--port"]).err(), Some("missing value for --port".to_string()));
+        assert_eq!(parse(&["--fast"]).err(), Some("unknown argument --fast".to_string()));
+        assert!(parse(&["--always-on-top", "yes"]).is_err());
+        // usage only
+        assert_eq!(parse(&["--help"]).err(), Some(String::new()));
+    }
+
+    #[test]
+    fn launch_options_are_not_saved() {
+        let saved = Settings {
+            port: "6776".to_string(),
+            ..Default::default()
+        };
+        let options = parse(&["--port", "7000", "--view", "engine", "--always-on-top", "false"]).unwrap();
+        let mut current = saved.clone();
+        options.apply(&mut current);
+        assert_eq!(current.port, "7000");
+        assert_eq!(current.view, DisplayState::Engine);
+        assert_eq!(options.persisted(&current, &saved), saved);
+
+        // changed in the menus after launch, so it's kept
+        current.view = DisplayState::Tires;
+        current.pressure_unit = crate::settings::PressureUnit::Psi;
+        let persisted = options.persisted(&current, &saved);
+        assert_eq!(persisted.view, DisplayState::Tires);
+        assert_eq!(persisted.pressure_unit, crate::settings::PressureUnit::Psi);
+        assert_eq!(persisted.port, "6776");
+        assert!(persisted.window.always_on_top);
+    }
+}
+
@@ -1,18 +1,84 @@
+use bevy::math::IVec2;
 use egui::{Vec2, Color32, Pos2};
+use rbr_telemetry::Channel;
+// gradient.rs
+use crate::gradient::TemperatureBands;
 
-pub const UDP_IP: &str = "127.0.0.1:";
+pub const UDP_IP: &str = "127.0.0.1";
+// the relay sends from an ephemeral port
+pub const RELAY_BIND: &str = "0.0.0.0:0";
+pub const RELAY_IP: &str = "127.0.0.1";
+pub const RECORDING_DIR: &str = "recordings";
+// upper bound on datagrams read per frame, so a flood can't stall the UI
+pub const MAX_PACKETS_PER_FRAME: usize = 1000;
+pub const STALE_STEP_WINDOW: u32 = 100;
+// seconds without packets before the plugin counts as not sending
+pub const LINK_STALL_TIME: f32 = 0.5;
+// seconds of packets with frozen race time, progress and step before the game counts as paused
+pub const LINK_PAUSE_TIME: f32 = 0.5;
+pub const LINK_TIMEOUT: f32 = 5.0;
+pub const RECORDING_EXTENSION: &str = "rbrs";
+pub const BESTS_DIR: &str = "bests";
+pub const BEST_EXTENSION: &str = "rbrb";
+pub const SETTINGS_PATH: &str = "settings.toml";
+pub const EXPORT_DIR: &str = "exports";
+pub const WINDOW_POSITION: IVec2 = IVec2::new(5, 40);
+pub const MIN_WINDOW_SCALE: f32 = 0.5;
+pub const MAX_WINDOW_SCALE: f32 = 3.0;
+pub const MIN_OPACITY: f32 = 0.2;
 pub const WIDTH: f32 = 400.0;
 pub const HEIGHT: f32 = 400.0;
 pub const ZERO: Pos2 = Pos2::new(0.0, 0.0);
 pub const HORIZONTAL_CENTER: f32 = 50.0;
 pub const VERTICAL_CENTER: f32 = 50.0;
 pub const TIRE_SIZE: Vec2 = Vec2::splat(100.0);
+pub const TIRE_LABEL_HEIGHT: f32 = 34.0;
+pub const TIRE_VIEW_SIZE: Vec2 = Vec2::new(400.0, 480.0);
+pub const PASCAL_PER_BAR: f32 = 100_000.0;
+pub const PASCAL_PER_PSI: f32 = 6_894.757;
+pub const TIRE_WEAR_COLOR: Color32 = Color32::from_rgba_premultiplied(0, 0, 0, 140);
 pub const BRAKE_SIZE: Vec2 = Vec2::new(25.0, 75.0);
-pub const SUSPENSION_SIZE: Vec2 = Vec2::new(50.0, 100.0);
-pub const GRAPH_SIZE: Vec2 = Vec2::new(600.0, 200.0);
-pub const DOT_SPACING: f32 = 0.75;
-pub const DOT_SIZE: Vec2 = Vec2::splat(DOT_SPACING);
-pub const LINE_SIZE: Vec2 = Vec2::new(600.0, DOT_SIZE.y / 2.0);
+pub const SUSPENSION_SIZE: Vec2 = Vec2::new(30.0, 100.0);
+pub const SUSPENSION_VIEW_SIZE: Vec2 = Vec2::new(440.0, 420.0);
+pub const SUSPENSION_COLOR: Color32 = Color32::from_rgb(60, 140, 220);
+pub const SUSPENSION_BG: Color32 = Color32::from_rgb(50, 50, 50);
+// spring deflection in meters, a generic range for cars without their own in settings.toml
+pub const MIN_SUSPENSION_TRAVEL: f32 = 0.05;
+pub const MAX_SUSPENSION_TRAVEL: f32 = 0.40;
+// how close to a travel limit counts as bottoming out
+pub const BOTTOM_OUT_MARGIN: f32 = 0.005;
+// piston velocity bins in m/s, ±0.5 m/s
+pub const DAMPER_BIN_WIDTH: f32 = 0.01;
+pub const DAMPER_BIN_COUNT: usize = 100;
+pub const DAMPER_BUMP_KNEE: f32 = 0.1;
+pub const DAMPER_REBOUND_KNEE: f32 = 0.1;
+pub const DAMPER_WINDOW: f32 = 30.0;
+pub const DAMPER_VIEW_SIZE: Vec2 = Vec2::new(600.0, 460.0);
+pub const HISTOGRAM_SIZE: Vec2 = Vec2::new(280.0, 120.0);
+pub const BUMP_COLOR: Color32 = Color32::from_rgb(220, 120, 40);
+pub const REBOUND_COLOR: Color32 = Color32::from_rgb(60, 140, 220);
+pub const PEDAL_VIEW_SIZE: Vec2 = Vec2::new(600.0, 360.0);
+pub const PEDAL_PLOT_SIZE: Vec2 = Vec2::new(580.0, 180.0);
+pub const GEAR_PLOT_SIZE: Vec2 = Vec2::new(580.0, 80.0);
+pub const PEDAL_GRAPH_SPAN: f32 = 10.0; // seconds
+pub const PEDAL_GRAPH_DISTANCE: f32 = 300.0; // meters
+pub const COMPARE_VIEW_SIZE: Vec2 = Vec2::new(600.0, 600.0);
+pub const COMPARE_PLOT_SIZE: Vec2 = Vec2::new(580.0, 130.0);
+// meters shown behind the car and of the reference ahead of it
+pub const COMPARE_BEHIND: f32 = 400.0;
+pub const COMPARE_AHEAD: f32 = 100.0;
+pub const REFERENCE_CHANNELS: [Channel; 4] = [Channel::THROTTLE, Channel::BRAKE, Channel::SPEED, Channel::GEAR];
+// reference traces are drawn at this share of the live color
+pub const REFERENCE_DIM: f32 = 0.45;
+pub const DELTA_BAR_SIZE: Vec2 = Vec2::new(240.0, 14.0);
+// seconds at either end of the delta bar
+pub const DELTA_BAR_RANGE: f32 = 2.0;
+pub const PLOT_INSET: f32 = 10.0;
+// room for the Y labels on the left and the X labels below
+pub const PLOT_MARGIN: Vec2 = Vec2::new(36.0, 14.0);
+pub const PLOT_X_TICKS: usize = 8;
+pub const PLOT_Y_TICKS: usize = 4;
+pub const PLOT_GRID_COLOR: Color32 = Color32::from_rgb(60, 60, 60);
 pub const SPACING: f32 = 50.0;
 pub const CHECKBOX_SPACING: f32 = 10.0;
 pub const WORD_SPACING: f32 = 70.0;
@@ -20,11 +86,54 @@ pub const BRAKE_SPACING: f32 = 5.0;
 pub const BRAKE_VERTICAL_SPACING: f32 = 10.0;
 pub const TIRE_HORIZONTAL_SPACING: f32 = 30.0;
 
-pub const MIN_TIRE_TEMP: f32 = 30.0;
-pub const MAX_TIRE_TEMP: f32 = 75.0;
+pub const GRAVEL_TIRE_BANDS: TemperatureBands = TemperatureBands {
+    cold: 30.0,
+    optimal: 55.0,
+    hot: 80.0,
+    overheat: 105.0,
+};
+pub const TARMAC_TIRE_BANDS: TemperatureBands = TemperatureBands {
+    cold: 40.0,
+    optimal: 75.0,
+    hot: 100.0,
+    overheat: 125.0,
+};
+pub const SNOW_TIRE_BANDS: TemperatureBands = TemperatureBands {
+    cold: 0.0,
+    optimal: 20.0,
+    hot: 45.0,
+    overheat: 70.0,
+};
+pub const BRAKE_BANDS: TemperatureBands = TemperatureBands {
+    cold: 100.0,
+    optimal: 300.0,
+    hot: 550.0,
+    overheat: 750.0,
+};
 
-pub const MIN_BRAKE_TEMP: f32 = 180.0;
-pub const MAX_BRAKE_TEMP: f32 = 360.0;
+// frames kept per channel, ten minutes at 60 Hz
+pub const HISTORY_CAPACITY: usize = 36000;
+pub const HISTORY_DURATION: f32 = 60.0; // seconds
+pub const MAX_HISTORY_DURATION: f32 = 600.0;
+
+pub const REDLINE: f32 = 7500.0;
+pub const SHIFT_LIGHT_START: f32 = 0.9;
+pub const SHIFT_LIGHTS: usize = 5;
+pub const ENGINE_OVERHEAT_TEMP: f32 = 110.0;
+// seconds of race time the overheating trend is measured over
+pub const ENGINE_TREND_WINDOW: f32 = 60.0;
+// °C per minute that counts as rising or falling
+pub const ENGINE_TREND_THRESHOLD: f32 = 0.5;
+pub const ENGINE_VIEW_SIZE: Vec2 = Vec2::new(400.0, 440.0);
+pub const RPM_GAUGE_SIZE: Vec2 = Vec2::new(260.0, 200.0);
+
+pub const BRAKE_FADE_TEMP: f32 = 600.0;
+pub const BRAKE_FADE_TIME: f32 = 3.0;
+
+pub const COLD_COLOR: Color32 = Color32::from_rgb(30, 60, 200);
+pub const OPTIMAL_COLOR: Color32 = Color32::from_rgb(40, 200, 60);
+pub const HOT_COLOR: Color32 = Color32::from_rgb(240, 160, 20);
+pub const OVERHEAT_COLOR: Color32 = Color32::from_rgb(220, 20, 20);
 
 pub const MENU_BG: Color32 = Color32::from_rgb(32,32,32);
 pub const LINE_COLOR: Color32 = Color32::GRAY;
\ No newline at end of file
@@ -0,0 +1,286 @@
+use egui::Color32;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+// constants.rs
+use crate::constants::*;
+
+// Piecewise linear color ramp, clamped to the first and last stop.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f32, Color32)>,
+}
+impl Gradient {
+    // Stops must be sorted by temperature.
+    pub fn new(stops: Vec<(f32, Color32)>) -> Gradient {
+        debug_assert!(!stops.is_empty());
+        debug_assert!(stops.windows(2).all(|w| w[0].0 <= w[1].0));
+        Gradient { stops }
+    }
+
+    pub fn color_at(&self, temperature: f32) -> Color32 {
+        let first = self.stops[0];
+        let last = self.stops[self.stops.len() - 1];
+        if temperature.is_nan() || temperature <= first.0 {
+            return first.1;
+        }
+        if temperature >= last.0 {
+            return last.1;
+        }
+        let i = self.stops.partition_point(|(t, _)| *t <= temperature);
+        let (t0, c0) = self.stops[i - 1];
+        let (t1, c1) = self.stops[i];
+        lerp_color(c0, c1, (temperature - t0) / (t1 - t0))
+    }
+}
+
+fn lerp_color(a: Color32, b: Color32, t: f32) -> Color32 {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color32::from_rgb(channel(a.r(), b.r()), channel(a.g(), b.g()), channel(a.b(), b.b()))
+}
+
+// Temperatures in °C where a tire or brake stops being cold, works best,
+// starts to run hot and overheats.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TemperatureBands {
+    pub cold: f32,
+    pub optimal: f32,
+    pub hot: f32,
+    pub overheat: f32,
+}
+impl TemperatureBands {
+    pub fn is_valid(&self) -> bool {
+        let t = [self.cold, self.optimal, self.hot, self.overheat];
+        t.iter().all(|t| t.is_finite()) && t.windows(2).all(|w| w[0] < w[1])
+    }
+
+    pub fn gradient(&self) -> Gradient {
+        Gradient::new(vec![
+            (self.cold, COLD_COLOR),
+            (self.optimal, OPTIMAL_COLOR),
+            (self.hot, HOT_COLOR),
+            (self.overheat, OVERHEAT_COLOR),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Compound {
+    Gravel,
+    Tarmac,
+    Snow,
+}
+impl Compound {
+    pub const ALL: [Compound; 3] = [Compound::Gravel, Compound::Tarmac, Compound::Snow];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Compound::Gravel => "Gravel",
+            Compound::Tarmac => "Tarmac",
+            Compound::Snow => "Snow",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompoundBands {
+    pub gravel: TemperatureBands,
+    pub tarmac: TemperatureBands,
+    pub snow: TemperatureBands,
+}
+impl CompoundBands {
+    pub fn get(&self, compound: Compound) -> TemperatureBands {
+        match compound {
+            Compound::Gravel => self.gravel,
+            Compound::Tarmac => self.tarmac,
+            Compound::Snow => self.snow,
+        }
+    }
+
+    fn validated(mut self) -> CompoundBands {
+        let default = CompoundBands::default();
+        for (bands, default) in [
+            (&mut self.gravel, default.gravel),
+            (&mut self.tarmac, default.tarmac),
+            (&mut self.snow, default.snow),
+        ] {
+            if !bands.is_valid() {
+                *bands = default;
+            }
+        }
+        self
+    }
+}
+impl Default for CompoundBands {
+    fn default() -> Self {
+        CompoundBands {
+            gravel: GRAVEL_TIRE_BANDS,
+            tarmac: TARMAC_TIRE_BANDS,
+            snow: SNOW_TIRE_BANDS,
+        }
+    }
+}
+
+// Overrides for one car, anything left out uses the general thresholds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CarThresholds {
+    pub gravel: Option<TemperatureBands>,
+    pub tarmac: Option<TemperatureBands>,
+    pub snow: Option<TemperatureBands>,
+    pub brakes: Option<TemperatureBands>,
+}
+impl CarThresholds {
+    fn tires(&self, compound: Compound) -> Option<TemperatureBands> {
+        match compound {
+            Compound::Gravel => self.gravel,
+            Compound::Tarmac => self.tarmac,
+            Compound::Snow => self.snow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    pub tires: CompoundBands,
+    pub brakes: TemperatureBands,
+    // keyed by the car index from the telemetry, as a string for the settings file
+    pub cars: HashMap<String, CarThresholds>,
+}
+impl Thresholds {
+    pub fn tire_bands(&self, compound: Compound, car: i32) -> TemperatureBands {
+        self.car(car)
+            .and_then(|c| c.tires(compound))
+            .unwrap_or_else(|| self.tires.get(compound))
+    }
+
+    pub fn brake_bands(&self, car: i32) -> TemperatureBands {
+        self.car(car).and_then(|c| c.brakes).unwrap_or(self.brakes)
+    }
+
+    fn car(&self, car: i32) -> Option<&CarThresholds> {
+        self.cars.get(&car.to_string())
+    }
+
+    // Invalid bands fall back to the defaults, invalid car overrides are dropped.
+    pub fn validated(mut self) -> Thresholds {
+        self.tires = self.tires.validated();
+        if !self.brakes.is_valid() {
+            self.brakes = BRAKE_BANDS;
+        }
+        for car in self.cars.values_mut() {
+            for bands in [&mut car.gravel, &mut car.tarmac, &mut car.snow, &mut car.brakes] {
+                if bands.is_some_and(|b| !b.is_valid()) {
+                    *bands = None;
+                }
+            }
+        }
+        self
+    }
+}
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            tires: CompoundBands::default(),
+            brakes: BRAKE_BANDS,
+            cars: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BANDS: TemperatureBands = TemperatureBands {
+        cold: 40.0,
+        optimal: 70.0,
+        hot: 90.0,
+        overheat: 110.0,
+    };
+
+    #[test]
+    fn clamps_outside_the_stops() {
+        let gradient = BANDS.gradient();
+        assert_eq!(gradient.color_at(-50.0), COLD_COLOR);
+        assert_eq!(gradient.color_at(40.0), COLD_COLOR);
+        assert_eq!(gradient.color_at(110.0), OVERHEAT_COLOR);
+        assert_eq!(gradient.color_at(1000.0), OVERHEAT_COLOR);
+        assert_eq!(gradient.color_at(f32::NAN), COLD_COLOR);
+    }
+
+    #[test]
+    fn hits_every_stop() {
+        let gradient = BANDS.gradient();
+        assert_eq!(gradient.color_at(70.0), OPTIMAL_COLOR);
+        assert_eq!(gradient.color_at(90.0), HOT_COLOR);
+    }
+
+    #[test]
+    fn interpolates_between_stops() {
+        let gradient = Gradient::new(vec![
+            (0.0, Color32::from_rgb(0, 0, 200)),
+            (100.0, Color32::from_rgb(200, 100, 0)),
+        ]);
+        assert_eq!(gradient.color_at(50.0), Color32::from_rgb(100, 50, 100));
+        assert_eq!(gradient.color_at(25.0), Color32::from_rgb(50, 25, 150));
+    }
+
+    // the old mapping multiplied the raw temperature and wrapped around above 85 °C
+    #[test]
+    fn channels_change_monotonically_within_a_segment() {
+        let gradient = BANDS.gradient();
+        let mut last = gradient.color_at(BANDS.cold);
+        let mut t = BANDS.cold;
+        while t <= BANDS.optimal {
+            let color = gradient.color_at(t);
+            assert!(color.b() <= last.b(), "blue rises at {t}");
+            assert!(color.g() >= last.g(), "green falls at {t}");
+            last = color;
+            t += 0.5;
+        }
+    }
+
+    #[test]
+    fn rejects_unordered_bands() {
+        assert!(BANDS.is_valid());
+        let swapped = TemperatureBands { hot: 60.0, ..BANDS };
+        assert!(!swapped.is_valid());
+        let nan = TemperatureBands { cold: f32::NAN, ..BANDS };
+        assert!(!nan.is_valid());
+    }
+
+    #[test]
+    fn car_overrides_fall_back_per_compound() {
+        let mut thresholds = Thresholds::default();
+        thresholds.cars.insert("3".to_string(), CarThresholds {
+            tarmac: Some(BANDS),
+            brakes: Some(BANDS),
+            ..Default::default()
+        });
+        assert_eq!(thresholds.tire_bands(Compound::Tarmac, 3), BANDS);
+        assert_eq!(thresholds.tire_bands(Compound::Gravel, 3), GRAVEL_TIRE_BANDS);
+        assert_eq!(thresholds.tire_bands(Compound::Tarmac, 4), TARMAC_TIRE_BANDS);
+        assert_eq!(thresholds.brake_bands(3), BANDS);
+        assert_eq!(thresholds.brake_bands(0), BRAKE_BANDS);
+    }
+
+    #[test]
+    fn invalid_settings_fall_back_to_defaults() {
+        let mut thresholds = Thresholds::default();
+        thresholds.tires.snow = TemperatureBands { optimal: -100.0, ..BANDS };
+        thresholds.brakes.overheat = f32::INFINITY;
+        thresholds.cars.insert("1".to_string(), CarThresholds {
+            gravel: Some(TemperatureBands { cold: 200.0, ..BANDS }),
+            tarmac: Some(BANDS),
+            ..Default::default()
+        });
+        let thresholds = thresholds.validated();
+        assert_eq!(thresholds.tires.snow, SNOW_TIRE_BANDS);
+        assert_eq!(thresholds.brakes, BRAKE_BANDS);
+        assert_eq!(thresholds.cars["1"].gravel, None);
+        assert_eq!(thresholds.cars["1"].tarmac, Some(BANDS));
+    }
+}
@@ -1,146 +1,323 @@
 
-use egui::{Color32, Pos2, Rect, Rounding, Sense, TextBuffer, Ui};
+use egui::{Align2, Color32, FontId, Pos2, Rect, Rounding, Sense, Shape, Stroke, Ui, Vec2};
 // constants.rs
 use crate::constants::*;
-use crate::telemetry::Suspension;
+use rbr_telemetry::{BrakeDisk, DamperHistogram, Suspension, Tire};
+// resources.rs
+use crate::resources::{LinkHealth, LinkStatus};
+// gradient.rs
+use crate::gradient::Gradient;
+// settings.rs
+use crate::settings::TravelRange;
+use std::time::Instant;
 
-pub fn create_line(
+// The tread as a strip of its 8 segments around the circumference, colored by
+// temperature with the worn share darkened from the right. The segment touching
+// the road is outlined.
+pub fn create_tire(
     ui: &mut Ui,
-    y: f32,
+    tire: &Tire,
+    gradient: &Gradient,
+    pressure: &str,
 ) {
-    ui.allocate_ui_at_rect(
-            Rect::from_two_pos(
-            Pos2::new(0.0, y),
-            Pos2::new(GRAPH_SIZE.x, y + LINE_SIZE.y)
-        ),
-        |ui| {
-            ui.painter().rect_filled(
-                Rect::from_two_pos(
-                    Pos2::new(0.0, y),
-                    Pos2::new(GRAPH_SIZE.x, y + LINE_SIZE.y)
-                ),
+    let size = TIRE_SIZE + Vec2::new(0.0, TIRE_LABEL_HEIGHT);
+    let (response, painter) = ui.allocate_painter(size, Sense::hover());
+    let strip = Rect::from_min_size(response.rect.min, TIRE_SIZE);
+    let height = TIRE_SIZE.y / 8.0;
+    for (i, segment) in tire.segments().into_iter().enumerate() {
+        let rect = Rect::from_min_size(
+            strip.min + Vec2::new(0.0, height * i as f32),
+            Vec2::new(TIRE_SIZE.x, height)
+        );
+        painter.rect_filled(rect, Rounding::same(0.0), gradient.color_at(segment.temperature));
+        let wear = (segment.wear / 100.0).clamp(0.0, 1.0);
+        if wear > 0.0 {
+            painter.rect_filled(
+                Rect::from_min_max(Pos2::new(rect.max.x - rect.width() * wear, rect.min.y), rect.max),
                 Rounding::same(0.0),
-                LINE_COLOR 
+                TIRE_WEAR_COLOR
             );
-        });
+        }
+    }
+    let current = Rect::from_min_size(
+        strip.min + Vec2::new(0.0, height * tire.current_segment.min(7) as f32),
+        Vec2::new(TIRE_SIZE.x, height)
+    );
+    painter.rect_stroke(current, Rounding::same(0.0), Stroke::new(2.0, Color32::WHITE));
+    painter.text(
+        Pos2::new(strip.center().x, strip.max.y + TIRE_LABEL_HEIGHT / 4.0),
+        Align2::CENTER_CENTER,
+        format!("T{:.0} C{:.0}", tire.tread_temperature, tire.carcass_temperature),
+        FontId::monospace(14.0),
+        Color32::WHITE
+    );
+    painter.text(
+        Pos2::new(strip.center().x, strip.max.y + TIRE_LABEL_HEIGHT * 0.75),
+        Align2::CENTER_CENTER,
+        pressure,
+        FontId::monospace(14.0),
+        Color32::WHITE
+    );
+    response.on_hover_ui(|ui| {
+        ui.label(format!("Tread {:.1} °C", tire.tread_temperature));
+        ui.label(format!("Carcass {:.1} °C", tire.carcass_temperature));
+        for (i, segment) in tire.segments().into_iter().enumerate() {
+            ui.label(format!("{} {:.1} °C {:.1}% worn", i + 1, segment.temperature, segment.wear));
+        }
+    });
 }
 
-pub fn create_dot(
+// Surface temperature on the left half, core (layer) temperature on the right,
+// worn share darkened from the top and a red outline while the disk is fading.
+pub fn create_brake(
     ui: &mut Ui,
-    x: f32,
-    y: f32,
-    color: Color32
+    disk: &BrakeDisk,
+    gradient: &Gradient,
+    fading: bool,
 ) {
-    ui.allocate_ui_at_rect(
-        Rect::from_center_size(
-            Pos2::new(x, y),
-            DOT_SIZE
-        ),
-        |ui| {
-            ui.painter().circle_filled(
-                Pos2::new(x, y), 
-                DOT_SIZE.x,
-                color 
-            );
-        });
+    let (response, painter) = ui.allocate_painter(BRAKE_SIZE, Sense::hover());
+    let rect = response.rect;
+    let (surface, core) = rect.split_left_right_at_fraction(0.5);
+    painter.rect_filled(surface, Rounding::same(0.0), gradient.color_at(disk.temperature));
+    painter.rect_filled(core, Rounding::same(0.0), gradient.color_at(disk.layer_temperature));
+    let wear = (disk.wear / 100.0).clamp(0.0, 1.0);
+    if wear > 0.0 {
+        painter.rect_filled(
+            Rect::from_min_max(rect.min, Pos2::new(rect.max.x, rect.min.y + rect.height() * wear)),
+            Rounding::same(0.0),
+            TIRE_WEAR_COLOR
+        );
+    }
+    if fading {
+        painter.rect_stroke(rect, Rounding::same(0.0), Stroke::new(2.0, Color32::RED));
+    }
+    response.on_hover_ui(|ui| {
+        ui.label(format!("Surface {:.0} °C", disk.temperature));
+        ui.label(format!("Core {:.0} °C", disk.layer_temperature));
+        ui.label(format!("Wear {:.1}%", disk.wear));
+    });
 }
 
-pub fn create_tire(
+// One corner: spring deflection as a bar between the travel limits, next to
+// the damper and force readouts.
+pub fn create_suspension(
     ui: &mut Ui,
-    temperature: f32,
+    name: &str,
+    suspension: &Suspension,
+    travel: &TravelRange,
 ) {
-    //println!("tire temp: {}", temperature);
-    let (response, painter) = ui.allocate_painter(TIRE_SIZE, Sense::hover());
-    let c = response.rect.center();
-    painter.rect_filled(
-        Rect::from_center_size(
-            c,
-            TIRE_SIZE
-        ), 
-        Rounding::same(0.0),
-        get_tire_color(temperature) 
-    );
+    let deflection = suspension.spring_deflection;
+    let bottomed = travel.is_at_limit(deflection);
+    let helper = suspension.helper_spring_is_active != 0;
+    ui.vertical(|ui| {
+        ui.label(name);
+        ui.horizontal(|ui| {
+            let (response, painter) = ui.allocate_painter(SUSPENSION_SIZE, Sense::hover());
+            let rect = response.rect;
+            painter.rect_filled(rect, Rounding::same(0.0), SUSPENSION_BG);
+            let color = if bottomed {
+                Color32::RED
+            } else if helper {
+                Color32::YELLOW
+            } else {
+                SUSPENSION_COLOR
+            };
+            let top = rect.max.y - rect.height() * travel.fraction(deflection);
+            painter.rect_filled(
+                Rect::from_min_max(Pos2::new(rect.min.x, top), rect.max),
+                Rounding::same(0.0),
+                color
+            );
+            let limit = Stroke::new(2.0, LINE_COLOR);
+            painter.hline(rect.x_range(), rect.min.y, limit);
+            painter.hline(rect.x_range(), rect.max.y, limit);
+            ui.vertical(|ui| {
+                ui.label(format!("{:.0} mm", deflection * 1000.0));
+                ui.label(format!("{:+.3} m/s", suspension.damper.piston_velocity));
+                ui.label(format!("Spring {:.0} N", suspension.spring_force));
+                ui.label(format!("Damper {:.0} N", suspension.damper_force));
+                ui.label(format!("Rollbar {:.0} N", suspension.rollbar_force));
+                ui.label(format!("Strut {:.0} N", suspension.strut_force));
+                let damage = format!("Damage {:.2}", suspension.damper.damage);
+                if suspension.damper.damage > 0.0 {
+                    ui.colored_label(Color32::RED, damage);
+                } else {
+                    ui.label(damage);
+                }
+            });
+        });
+        if bottomed {
+            ui.colored_label(Color32::RED, "Bottoming out!");
+        } else if helper {
+            ui.colored_label(Color32::YELLOW, "Helper spring");
+        } else {
+            ui.label("");
+        }
+    });
 }
 
-pub fn create_brake(
+// Bin counts from full rebound on the left to full bump on the right, with
+// zero and the knee speeds marked and the band shares underneath.
+pub fn create_histogram(
     ui: &mut Ui,
-    temperature: f32,
+    name: &str,
+    histogram: &DamperHistogram,
+    corner: usize,
+    bump_knee: f32,
+    rebound_knee: f32,
 ) {
-    //println!("brake temp: {}", temperature);
-    let (response, painter) = ui.allocate_painter(BRAKE_SIZE, Sense::hover());
-    let c = response.rect.center();
-    painter.rect_filled(
-        Rect::from_center_size(
-            c,
-            BRAKE_SIZE
-        ), 
-        Rounding::same(0.0),
-        get_brake_color(temperature) 
-    );
+    ui.vertical(|ui| {
+        ui.label(name);
+        let (response, painter) = ui.allocate_painter(HISTOGRAM_SIZE, Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, Rounding::same(0.0), SUSPENSION_BG);
+        let counts = histogram.counts(corner);
+        let max = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+        let width = rect.width() / counts.len() as f32;
+        for (bin, &count) in counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let (min, _) = histogram.bin_range(bin);
+            let color = if min >= 0.0 { BUMP_COLOR } else { REBOUND_COLOR };
+            let x = rect.min.x + width * bin as f32;
+            let height = rect.height() * count as f32 / max;
+            painter.rect_filled(
+                Rect::from_min_max(Pos2::new(x, rect.max.y - height), Pos2::new(x + width, rect.max.y)),
+                Rounding::same(0.0),
+                color
+            );
+        }
+        let range = histogram.bin_width * histogram.bin_count as f32;
+        let x = |velocity: f32| rect.min.x + rect.width() * (velocity / range + 0.5);
+        painter.vline(x(0.0), rect.y_range(), Stroke::new(1.0, Color32::WHITE));
+        painter.vline(x(bump_knee), rect.y_range(), Stroke::new(1.0, LINE_COLOR));
+        painter.vline(x(-rebound_knee), rect.y_range(), Stroke::new(1.0, LINE_COLOR));
+        let bands = histogram.bands(corner, bump_knee, rebound_knee);
+        ui.label(format!(
+            "HS {:.0}% LS {:.0}% | LS {:.0}% HS {:.0}%",
+            bands.high_rebound * 100.0,
+            bands.low_rebound * 100.0,
+            bands.low_bump * 100.0,
+            bands.high_bump * 100.0
+        ));
+    });
 }
 
-pub fn create_suspension(
+// Dial from 0 to `max` rpm over a 240° sweep, red from the redline up.
+pub fn create_rpm_gauge(
     ui: &mut Ui,
-    spring: Suspension,
+    rpm: f32,
+    redline: f32,
+    max: f32,
 ) {
-    let (response, painter) = ui.allocate_painter(SUSPENSION_SIZE, Sense::hover());
-    let c = response.rect.center();
-    painter.rect_filled(
-        Rect::from_center_size(
-            c,
-            SUSPENSION_SIZE
-        ), 
-        Rounding::same(0.0),
-        Color32::RED
+    let (response, painter) = ui.allocate_painter(RPM_GAUGE_SIZE, Sense::hover());
+    let center = response.rect.center() + Vec2::new(0.0, 15.0);
+    let radius = RPM_GAUGE_SIZE.y * 0.45;
+    let start = 150.0_f32.to_radians();
+    let sweep = 240.0_f32.to_radians();
+    let point = |rpm: f32, r: f32| {
+        let angle = start + sweep * (rpm / max).clamp(0.0, 1.0);
+        center + Vec2::angled(angle) * r
+    };
+    let arc = |from: f32, to: f32, color: Color32| {
+        let points = (0..=32).map(|i| point(from + (to - from) * i as f32 / 32.0, radius)).collect();
+        painter.add(Shape::line(points, Stroke::new(4.0, color)));
+    };
+    arc(0.0, redline, LINE_COLOR);
+    arc(redline, max, Color32::RED);
+    let mut tick = 0.0;
+    while tick <= max {
+        painter.line_segment([point(tick, radius - 10.0), point(tick, radius)], Stroke::new(2.0, LINE_COLOR));
+        painter.text(
+            point(tick, radius - 22.0),
+            Align2::CENTER_CENTER,
+            format!("{}", (tick / 1000.0) as u32),
+            FontId::monospace(12.0),
+            Color32::WHITE
+        );
+        tick += 1000.0;
+    }
+    painter.line_segment([center, point(rpm, radius - 5.0)], Stroke::new(3.0, Color32::WHITE));
+    painter.text(
+        center + Vec2::new(0.0, radius * 0.5),
+        Align2::CENTER_CENTER,
+        format!("{rpm:.0}"),
+        FontId::monospace(24.0),
+        Color32::WHITE
     );
 }
 
-
-
-pub fn get_tire_color(temperature: f32) -> Color32 {
-    if temperature > MAX_TIRE_TEMP {
-        return Color32::LIGHT_GREEN;
+// Lights fill up from `start` rpm to the redline and all blink at the redline.
+pub fn create_shift_light(
+    ui: &mut Ui,
+    rpm: f32,
+    start: f32,
+    redline: f32,
+) {
+    let size = Vec2::new(RPM_GAUGE_SIZE.x, 30.0);
+    let (response, painter) = ui.allocate_painter(size, Sense::hover());
+    let rect = response.rect;
+    let spacing = rect.width() / SHIFT_LIGHTS as f32;
+    let lit = ((rpm - start) / (redline - start) * SHIFT_LIGHTS as f32).ceil().clamp(0.0, SHIFT_LIGHTS as f32) as usize;
+    let blink = (ui.input(|i| i.time * 8.0) as u64).is_multiple_of(2);
+    for i in 0..SHIFT_LIGHTS {
+        let color = if rpm >= redline {
+            if blink { Color32::RED } else { SUSPENSION_BG }
+        } else if i < lit {
+            if i + 1 == SHIFT_LIGHTS { Color32::RED } else if i * 2 >= SHIFT_LIGHTS { Color32::YELLOW } else { Color32::GREEN }
+        } else {
+            SUSPENSION_BG
+        };
+        let c = Pos2::new(rect.min.x + spacing * (i as f32 + 0.5), rect.center().y);
+        painter.circle_filled(c, size.y * 0.4, color);
     }
-    if temperature < MIN_TIRE_TEMP {
-        return Color32::DARK_BLUE;
+    // keep blinking without new telemetry
+    if rpm >= redline {
+        ui.ctx().request_repaint();
     }
-    let b: u8 = 255 - (temperature * 3.0) as u8;
-    let g: u8 = (temperature * 3.0) as u8;
-    Color32::from_rgb(0, g, b)
 }
 
-fn get_brake_color(temperature: f32) -> Color32 {
-    if temperature > MAX_BRAKE_TEMP {
-        return Color32::LIGHT_GREEN;
-    }
-    if temperature < MIN_BRAKE_TEMP {
-        return Color32::DARK_BLUE;
+// Grows from the middle, green to the left when ahead of the best and red to the
+// right when behind, full at DELTA_BAR_RANGE seconds.
+pub fn create_delta_bar(
+    ui: &mut Ui,
+    delta: Option<f32>,
+) {
+    let (response, painter) = ui.allocate_painter(DELTA_BAR_SIZE, Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, Rounding::same(2.0), SUSPENSION_BG);
+    if let Some(delta) = delta.filter(|d| d.is_finite()) {
+        let width = (delta / DELTA_BAR_RANGE).clamp(-1.0, 1.0) * rect.width() * 0.5;
+        let center = rect.center().x;
+        let bar = Rect::from_x_y_ranges(center.min(center + width)..=center.max(center + width), rect.y_range());
+        let color = if delta > 0.0 { Color32::RED } else { Color32::GREEN };
+        painter.rect_filled(bar, Rounding::same(2.0), color);
     }
-    let b: u8 = 255 - (temperature / 2.0) as u8;
-    let g: u8 = (temperature / 2.0) as u8;
-    Color32::from_rgb(0, g, b)
+    painter.line_segment([rect.center_top(), rect.center_bottom()], Stroke::new(1.0, Color32::WHITE));
 }
 
+pub fn create_link_badge(
+    ui: &mut Ui,
+    link: &LinkHealth,
+    packets_per_second: f32,
+) {
+    let text = match link.status {
+        LinkStatus::Live => format!("{} {packets_per_second:.0}/s", link.status.label()),
+        LinkStatus::Waiting => link.status.label().to_string(),
+        _ => match link.since_last_packet(Instant::now()) {
+            Some(since) => format!("{} {:.1}s", link.status.label(), since.as_secs_f32()),
+            None => link.status.label().to_string(),
+        },
+    };
+    ui.colored_label(get_link_color(link.status), text);
+}
 
-
-pub fn format_time(minutes: f32, seconds: f32) -> String {
-    let mut time: String = String::new(); 
-    if minutes < 10.0 {
-        time.push_str(format!("0{}", minutes).as_str());
-    } else {
-        time.push_str(format!("{}", minutes).as_str());
-    }
-    time.push_str(":".as_str());
-    if seconds < 10.0 {
-        time.push_str(format!("0{}", seconds).as_str());
-    } else {
-        time.push_str(format!("{}", seconds).as_str());
+pub fn get_link_color(status: LinkStatus) -> Color32 {
+    match status {
+        LinkStatus::Live => Color32::GREEN,
+        LinkStatus::Paused => Color32::YELLOW,
+        LinkStatus::NotSending => Color32::RED,
+        LinkStatus::Waiting => Color32::GRAY,
     }
-    let remainder = (seconds.fract() * 100.0).round();
-    if remainder == 0.0 {
-        time.push_str(".00".as_str());
-    } else if remainder % 10.0 == 0.0 {
-        time.push_str("0".as_str());
-    }
-    time
-}
\ No newline at end of file
+}
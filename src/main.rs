@@ -1,5 +1,7 @@
 // Hide Terminal
 //#![windows_subsystem = "windows"]
+// Bevy systems take every resource they use as an argument
+#![allow(clippy::too_many_arguments)]
 
 // helper.rs
 mod helper;
@@ -9,9 +11,7 @@ use helper::*;
 mod udp_connection;
 use udp_connection::*;
 
-// telemetry.rs
-mod telemetry;
-use telemetry::*;
+use rbr_telemetry::*;
 
 // constants.rs
 mod constants;
@@ -21,9 +21,36 @@ use constants::*;
 mod resources;
 use resources::*;
 
+// recorder.rs
+mod recorder;
+use recorder::*;
+
+// replay.rs
+mod replay;
+use replay::*;
+
+// relay.rs
+mod relay;
+use relay::*;
+
+// settings.rs
+mod settings;
+use settings::*;
+
+// cli.rs
+mod cli;
+
+// gradient.rs
+mod gradient;
+use gradient::*;
+
+// plot.rs
+mod plot;
+use plot::*;
+
 // UI
 use bevy::{
-    prelude::*, time::common_conditions::on_timer, utils::Duration, window::WindowLevel, winit::WinitSettings, winit::UpdateMode, render::settings::RenderCreation, render::settings::WgpuSettings, render::RenderPlugin, render::settings::Backends
+    prelude::*, time::common_conditions::on_timer, utils::Duration, winit::WinitSettings, winit::UpdateMode, render::settings::RenderCreation, render::settings::WgpuSettings, render::RenderPlugin, render::settings::Backends
 };
 use bevy_egui::{
     EguiContexts, 
@@ -33,19 +60,47 @@ use egui::{
     Color32, 
     FontId, 
     Frame, 
+    Grid,
     Margin,
 };
 
 fn main() {
+    let mut options = match cli::Options::parse() {
+        Ok(options) => options,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{e}");
+            }
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(2);
+        },
+    };
+    // the file stays as it was, launch options only change this run
+    let saved = Settings::load();
+    let mut settings = saved.clone();
+    options.apply(&mut settings);
+    let mut recorder = Recorder::default();
+    if let Some(path) = options.record.take() {
+        recorder.start_to(path);
+    }
+    let mut replay = Replay::default();
+    let mut source = SourceState::Live;
+    if let Some(path) = options.replay.take() {
+        replay.path = path.display().to_string();
+        if replay.load() {
+            source = SourceState::Replay;
+        }
+    }
+    let mut reference = Reference::default();
+    if let Some(path) = options.reference.take() {
+        reference.path = path.display().to_string();
+        // no live stage yet, compare_menu picks the run again once there is
+        reference.load(None);
+    }
     App::new()
         .insert_resource(ClearColor(Color::NONE))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                resizable: false,
-                position: WindowPosition::At(IVec2 { x: 5, y: 40 }),
-                window_level: WindowLevel::AlwaysOnTop,
-                ..default()
-            }),
+            primary_window: Some(settings.window()),
             ..default()
         })
         .set(RenderPlugin {
@@ -63,20 +118,42 @@ fn main() {
         })
         .insert_state(DisplayState::Main)
         .insert_state(ConnectionState::Disconnected)
+        .insert_state(source)
         .init_resource::<Socket>()
         .init_resource::<RBR>()
         .init_resource::<Port>()
-        .init_resource::<Pedals>()
+        .init_resource::<LinkHealth>()
+        .init_resource::<History>()
+        .init_resource::<InputHistory>()
+        .init_resource::<Dampers>()
+        .init_resource::<Pressures>()
+        .init_resource::<Brakes>()
+        .init_resource::<EngineHistory>()
         .init_resource::<PedalCheckboxes>()
+        .insert_resource(recorder)
+        .insert_resource(replay)
+        .insert_resource(reference)
+        .insert_resource(Bests::load())
+        .init_resource::<Relay>()
+        .insert_resource(settings)
+        .insert_resource(SavedSettings(saved))
+        .insert_resource(options)
+        .add_systems(Startup, apply_settings)
         .add_systems(
             Update,
             (   
                 
                 telemetry_handler
+                    .run_if(in_state(SourceState::Live))
                     .run_if(in_state(ConnectionState::Connected)),
                 connect_udp
+                    .run_if(in_state(SourceState::Live))
                     .run_if(in_state(ConnectionState::Disconnected))
-                    .run_if(on_timer(Duration::from_secs(2)))
+                    .run_if(on_timer(Duration::from_secs(2))),
+                replay_handler
+                    .run_if(in_state(SourceState::Replay)),
+                save_settings
+                    .run_if(on_timer(Duration::from_secs(1))),
                     )
         )
         
@@ -85,7 +162,10 @@ fn main() {
                 main_menu.run_if(in_state(DisplayState::Main)),
                 pedal_menu.run_if(in_state(DisplayState::Pedals)),
                 tire_menu.run_if(in_state(DisplayState::Tires)),
-                suspension_menu.run_if(in_state(DisplayState::Suspension))
+                suspension_menu.run_if(in_state(DisplayState::Suspension)),
+                damper_menu.run_if(in_state(DisplayState::Dampers)),
+                engine_menu.run_if(in_state(DisplayState::Engine)),
+                compare_menu.run_if(in_state(DisplayState::Compare))
         )
     )
     .run();
@@ -98,28 +178,32 @@ fn pedal_menu(
     mut egui_ctx: EguiContexts,
     mut next_state: ResMut<NextState<DisplayState>>,
     rbr: Res<RBR>,
-    mut pedals: ResMut<Pedals>,
-    mut checkboxes: ResMut<PedalCheckboxes>
+    history: Res<History>,
+    inputs: Res<InputHistory>,
+    mut checkboxes: ResMut<PedalCheckboxes>,
+    source: Res<State<SourceState>>,
+    mut replay: ResMut<Replay>,
+    link: Res<LinkHealth>,
+    mut settings: ResMut<Settings>,
 ) {
-    pedals.add_data(&rbr.telemetry.control);
     let mut window = windows.single_mut();
-    window.resolution.set(GRAPH_SIZE.x, GRAPH_SIZE.y);
+    window.resolution.set(PEDAL_VIEW_SIZE.x, PEDAL_VIEW_SIZE.y);
     let gui = egui::Window::new("gui")
         .title_bar(false)
         .fixed_pos(ZERO)
-        .default_height(GRAPH_SIZE.y)
-        .default_width(GRAPH_SIZE.x)
+        .default_height(PEDAL_VIEW_SIZE.y)
+        .default_width(PEDAL_VIEW_SIZE.x)
         .collapsible(false)
         .frame(Frame {
-            fill: MENU_BG,
+            fill: settings.background(),
             inner_margin: Margin::same(0.0),
             outer_margin: Margin::same(0.0),
             ..default()
         });
     
     gui.show(egui_ctx.ctx_mut(), |ui| {
-        ui.set_height(GRAPH_SIZE.y);
-        ui.set_width(GRAPH_SIZE.x);
+        ui.set_height(PEDAL_VIEW_SIZE.y);
+        ui.set_width(PEDAL_VIEW_SIZE.x);
         ui.style_mut()
             .override_font_id = Some(FontId::new(
                 16.0,
@@ -132,6 +216,25 @@ fn pedal_menu(
                     next_state.set(DisplayState::Main);
                 }
             });
+            ui.horizontal(|ui| {
+                ui.add_space(HORIZONTAL_CENTER);
+                match source.get() {
+                    SourceState::Live => create_link_badge(ui, &link, rbr.stats.packets_per_second),
+                    SourceState::Replay => create_replay_bar(ui, &mut replay),
+                }
+                let mut axis = settings.graph_axis;
+                egui::ComboBox::from_id_source("graph axis")
+                    .selected_text(axis.label())
+                    .show_ui(ui, |ui| {
+                        for a in XAxis::ALL {
+                            ui.selectable_value(&mut axis, a, a.label());
+                        }
+                    });
+                settings.graph_axis = axis;
+                if inputs.is_frozen() {
+                    ui.colored_label(Color32::GRAY, "race time stopped, showing clock time");
+                }
+            });
             ui.horizontal(|ui| {
                 ui.add_space(HORIZONTAL_CENTER);
                 ui.colored_label(Color32::GREEN, "Throttle");
@@ -159,79 +262,81 @@ fn pedal_menu(
                 
             
         
-        ui.vertical(|ui| {
-            create_line(ui, GRAPH_SIZE.y - DOT_SIZE.y);
-            create_line(ui, GRAPH_SIZE.y - 33.4);
-            create_line(ui, GRAPH_SIZE.y - 66.6);
-            create_line(ui, GRAPH_SIZE.y - 100.0);
-            for i in 0..pedals.size {
-                if checkboxes.throttle {
-                    create_dot(
-                        ui, 
-                        i as f32,
-                        GRAPH_SIZE.y - (pedals.throttle[i as usize]),
-                        Color32::GREEN
-                    );
-                }
-                if checkboxes.brake {
-                    create_dot(
-                        ui, 
-                        i as f32, 
-                        GRAPH_SIZE.y - (pedals.brake[i as usize]),
-                        Color32::RED
-                    );
-                }
-                if checkboxes.handbrake {
-                    create_dot(
-                        ui, 
-                        i as f32, 
-                        GRAPH_SIZE.y - (pedals.handbrake[i as usize]),
-                        Color32::BLUE
-                    );
-                }
-                if checkboxes.clutch {
-                    create_dot(
-                        ui, 
-                        i as f32, 
-                        GRAPH_SIZE.y - (pedals.clutch[i as usize]),
-                        Color32::LIGHT_BLUE
-                    );
-                }
-                if checkboxes.gear {
-                    create_dot(
-                        ui, 
-                        i as f32, 
-                        GRAPH_SIZE.y - (((pedals.gear[i as usize]) as f32) * 15.0),
-                        Color32::YELLOW
-                    );
-                }
-            }
+        // with race time standing still the pedals would pile up on one point
+        let (history, axis) = if inputs.is_frozen() {
+            (&inputs.history, XAxis::Time)
+        } else {
+            (&history.0, settings.graph_axis)
+        };
+        let graphs = [
+            (checkboxes.throttle, Channel::THROTTLE, Color32::GREEN),
+            (checkboxes.brake, Channel::BRAKE, Color32::RED),
+            (checkboxes.handbrake, Channel::HANDBRAKE, Color32::BLUE),
+            (checkboxes.clutch, Channel::CLUTCH, Color32::LIGHT_BLUE),
+        ];
+        let pedals: Vec<Series> = graphs.into_iter()
+            .filter(|(shown, _, _)| *shown)
+            .map(|(_, channel, color)| Series::from_history(history, channel, axis, color))
+            .collect();
+        let span = match axis {
+            XAxis::Time => PEDAL_GRAPH_SPAN,
+            XAxis::Distance | XAxis::DistanceToEnd => PEDAL_GRAPH_DISTANCE,
+        };
+        let x_range = axis.scrolling(history, span);
+        ui.horizontal(|ui| {
+            ui.add_space(PLOT_INSET);
+            Plot::new(PEDAL_PLOT_SIZE)
+                .x_range(x_range)
+                .y_range(Range::Fixed(0.0, 100.0))
+                .x_unit(axis.unit())
+                .y_unit("%")
+                .show(ui, &pedals);
         });
+        if checkboxes.gear {
+            ui.horizontal(|ui| {
+                ui.add_space(PLOT_INSET);
+                Plot::new(GEAR_PLOT_SIZE)
+                    .x_range(x_range)
+                    .y_range(Range::Fixed(-1.0, 7.0))
+                    .x_unit(axis.unit())
+                    .y_unit("Gear")
+                    .legend(false)
+                    .show(ui, &[Series::from_history(history, Channel::GEAR, axis, Color32::YELLOW)]);
+            });
+        }
     });
 }
 
 
 fn suspension_menu(
+    mut windows: Query<&mut Window>,
     mut egui_ctx: EguiContexts,
     mut next_state: ResMut<NextState<DisplayState>>,
-    rbr: Res<RBR>
+    rbr: Res<RBR>,
+    source: Res<State<SourceState>>,
+    mut replay: ResMut<Replay>,
+    link: Res<LinkHealth>,
+    settings: Res<Settings>,
 ) {
+    let mut window = windows.single_mut();
+    window.resolution.set(SUSPENSION_VIEW_SIZE.x, SUSPENSION_VIEW_SIZE.y);
     let gui = egui::Window::new("gui")
         .title_bar(false)
         .fixed_pos(ZERO)
-        .default_height(HEIGHT)
-        .default_width(WIDTH)
+        .default_height(SUSPENSION_VIEW_SIZE.y)
+        .default_width(SUSPENSION_VIEW_SIZE.x)
         .collapsible(false)
         .frame(Frame {
-            fill: MENU_BG,
+            fill: settings.background(),
             inner_margin: Margin::same(0.0),
             outer_margin: Margin::same(0.0),
             ..default()
         });
     gui.show(egui_ctx.ctx_mut(), |ui| {
+        ui.set_width(SUSPENSION_VIEW_SIZE.x);
         ui.style_mut()
             .override_font_id = Some(FontId::new(
-                20.0,
+                13.0,
                  egui::FontFamily::Monospace
         ));
         ui.vertical_centered(|ui| {
@@ -241,30 +346,429 @@ fn suspension_menu(
                 next_state.set(DisplayState::Main);
             }
         });
+        ui.horizontal(|ui| {
+            ui.add_space(HORIZONTAL_CENTER);
+            match source.get() {
+                SourceState::Live => create_link_badge(ui, &link, rbr.stats.packets_per_second),
+                SourceState::Replay => create_replay_bar(ui, &mut replay),
+            }
+        });
+        let car = &rbr.telemetry.car;
+        let travel = settings.suspension.travel(car.index);
+        ui.add_space(BRAKE_VERTICAL_SPACING);
+        ui.columns(2, |columns| {
+            create_suspension(&mut columns[0], "Front left", &car.suspension_lf, &travel);
+            create_suspension(&mut columns[1], "Front right", &car.suspension_rf, &travel);
+        });
+        ui.add_space(BRAKE_VERTICAL_SPACING);
+        ui.columns(2, |columns| {
+            create_suspension(&mut columns[0], "Rear left", &car.suspension_lb, &travel);
+            create_suspension(&mut columns[1], "Rear right", &car.suspension_rb, &travel);
+        });
+    });
+}
 
-        
-        ui.vertical(|ui| {
-            ui.add_space(BRAKE_VERTICAL_SPACING);
-            let sus_LF = rbr.telemetry.car.suspension_lf;
-            create_suspension(ui, sus_LF);
-        });  
+
+fn engine_menu(
+    mut windows: Query<&mut Window>,
+    mut egui_ctx: EguiContexts,
+    mut next_state: ResMut<NextState<DisplayState>>,
+    rbr: Res<RBR>,
+    source: Res<State<SourceState>>,
+    mut replay: ResMut<Replay>,
+    link: Res<LinkHealth>,
+    trend: Res<EngineHistory>,
+    settings: Res<Settings>,
+) {
+    let mut window = windows.single_mut();
+    window.resolution.set(ENGINE_VIEW_SIZE.x, ENGINE_VIEW_SIZE.y);
+    let gui = egui::Window::new("gui")
+        .title_bar(false)
+        .fixed_pos(ZERO)
+        .default_height(ENGINE_VIEW_SIZE.y)
+        .default_width(ENGINE_VIEW_SIZE.x)
+        .collapsible(false)
+        .frame(Frame {
+            fill: settings.background(),
+            inner_margin: Margin::same(0.0),
+            outer_margin: Margin::same(0.0),
+            ..default()
+        });
+    gui.show(egui_ctx.ctx_mut(), |ui| {
+        ui.set_width(ENGINE_VIEW_SIZE.x);
+        ui.vertical_centered(|ui| {
+            ui.add_space(SPACING * 0.1);
+            let back = ui.button("Back");
+            if back.clicked() {
+                next_state.set(DisplayState::Main);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add_space(HORIZONTAL_CENTER);
+            match source.get() {
+                SourceState::Live => create_link_badge(ui, &link, rbr.stats.packets_per_second),
+                SourceState::Replay => create_replay_bar(ui, &mut replay),
+            }
+        });
+        let engine = &rbr.telemetry.car.engine;
+        let redline = settings.engine.redline(rbr.telemetry.car.index);
+        let max = (redline / 1000.0).ceil() * 1000.0 + 1000.0;
+        ui.vertical_centered(|ui| {
+            create_shift_light(ui, engine.rpm, redline * settings.engine.shift_light, redline);
+            create_rpm_gauge(ui, engine.rpm, redline, max);
+        });
+        let rate = trend.rate();
+        let overheat = settings.engine.overheat_temperature;
+        // the radiator has no trend, it follows the coolant
+        let rows = [
+            ("Coolant", engine.engine_coolant_temperature, Some(rate.map(|r| r.0))),
+            ("Radiator", engine.radiator_coolant_temperature, None),
+            ("Engine", engine.engine_temperature, Some(rate.map(|r| r.1))),
+        ];
+        Grid::new("engine temperatures").show(ui, |ui| {
+            for (name, temperature, trend) in rows {
+                ui.add_space(HORIZONTAL_CENTER);
+                ui.label(name);
+                ui.label(format!("{temperature:.1} °C"));
+                match trend {
+                    Some(Some(rate)) => {
+                        let (arrow, color) = if rate > ENGINE_TREND_THRESHOLD {
+                            // rising past the limit is about to overheat
+                            ("↑", if temperature >= overheat { Color32::RED } else { Color32::YELLOW })
+                        } else if rate < -ENGINE_TREND_THRESHOLD {
+                            ("↓", COLD_COLOR)
+                        } else {
+                            ("→", Color32::WHITE)
+                        };
+                        ui.colored_label(color, format!("{arrow} {rate:+.1} °C/min"));
+                    }
+                    Some(None) => {
+                        ui.label("--");
+                    }
+                    None => {
+                        ui.label("");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        if engine.engine_coolant_temperature >= overheat {
+            ui.vertical_centered(|ui| {
+                ui.colored_label(Color32::RED, "Overheating");
+            });
+        }
     });
 }
 
 
+fn compare_menu(
+    mut windows: Query<&mut Window>,
+    mut egui_ctx: EguiContexts,
+    mut next_state: ResMut<NextState<DisplayState>>,
+    rbr: Res<RBR>,
+    history: Res<History>,
+    mut reference: ResMut<Reference>,
+    source: Res<State<SourceState>>,
+    mut replay: ResMut<Replay>,
+    link: Res<LinkHealth>,
+    settings: Res<Settings>,
+) {
+    let mut window = windows.single_mut();
+    window.resolution.set(COMPARE_VIEW_SIZE.x, COMPARE_VIEW_SIZE.y);
+    let gui = egui::Window::new("gui")
+        .title_bar(false)
+        .fixed_pos(ZERO)
+        .default_height(COMPARE_VIEW_SIZE.y)
+        .default_width(COMPARE_VIEW_SIZE.x)
+        .collapsible(false)
+        .frame(Frame {
+            fill: settings.background(),
+            inner_margin: Margin::same(0.0),
+            outer_margin: Margin::same(0.0),
+            ..default()
+        });
+    // a run on the stage and in the car being driven, once there are packets
+    let live = rbr.protocol.map(|_| (rbr.telemetry.stage.index, rbr.telemetry.car.index));
+    reference.follow(live);
+    gui.show(egui_ctx.ctx_mut(), |ui| {
+        ui.set_width(COMPARE_VIEW_SIZE.x);
+        ui.vertical_centered(|ui| {
+            ui.add_space(SPACING * 0.1);
+            let back = ui.button("Back");
+            if back.clicked() {
+                next_state.set(DisplayState::Main);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add_space(HORIZONTAL_CENTER);
+            match source.get() {
+                SourceState::Live => create_link_badge(ui, &link, rbr.stats.packets_per_second),
+                SourceState::Replay => create_replay_bar(ui, &mut replay),
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add_space(PLOT_INSET);
+            ui.label("Reference");
+            let path = ui.add(
+                egui::TextEdit::singleline(&mut reference.path)
+                    .desired_width(300.0)
+                    .hint_text("recordings/session.rbrs")
+            );
+            let enter = path.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Load").clicked() || enter {
+                reference.load(live);
+            }
+            if reference.run.is_some() && ui.button("Clear").clicked() {
+                reference.run = None;
+            }
+        });
+        let stage = &rbr.telemetry.stage;
+        let car = rbr.telemetry.car.index;
+        ui.horizontal(|ui| {
+            ui.add_space(PLOT_INSET);
+            if let Some(e) = &reference.error {
+                ui.colored_label(Color32::RED, e);
+                return;
+            }
+            let Some(run) = &reference.run else {
+                ui.label("Load a recorded session to compare against");
+                return;
+            };
+            let end = run.end_time();
+            ui.label(format!(
+                "Stage {}, car {}, {} over {:.0} m",
+                run.stage_index,
+                run.car_index,
+                format_time((end / 60.0).floor(), end % 60.0),
+                run.end_distance()
+            ));
+        });
+        let Some(run) = &reference.run else {
+            return;
+        };
+        // a run on another stage or in another car has nothing to compare against
+        if run.stage_index != stage.index || run.car_index != car {
+            ui.horizontal(|ui| {
+                ui.add_space(PLOT_INSET);
+                ui.colored_label(
+                    Color32::YELLOW,
+                    format!("Live is stage {}, car {}, the session has no run of it", stage.index, car),
+                );
+            });
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.add_space(PLOT_INSET);
+            ui.style_mut().override_font_id = Some(FontId::monospace(24.0));
+            match run.delta(stage.progress, stage.race_time) {
+                Some(delta) => {
+                    let color = if delta > 0.0 { Color32::RED } else { Color32::GREEN };
+                    ui.colored_label(color, format!("{delta:+.2} s"));
+                },
+                None => {
+                    ui.label("--.-- s");
+                },
+            }
+        });
+
+        let x_range = Range::Fixed(stage.progress - COMPARE_BEHIND, stage.progress + COMPARE_AHEAD);
+        let live = |channel, color| Series::from_history(&history, channel, XAxis::Distance, color);
+        let plots = [
+            (
+                vec![
+                    live(Channel::THROTTLE, Color32::GREEN),
+                    Series::from_profile(run, Channel::THROTTLE, Color32::GREEN),
+                    live(Channel::BRAKE, Color32::RED),
+                    Series::from_profile(run, Channel::BRAKE, Color32::RED),
+                ],
+                Range::Fixed(0.0, 100.0),
+                "%",
+            ),
+            (
+                vec![
+                    live(Channel::SPEED, Color32::WHITE),
+                    Series::from_profile(run, Channel::SPEED, Color32::WHITE),
+                ],
+                Range::Auto,
+                Channel::SPEED.unit(),
+            ),
+            (
+                vec![
+                    live(Channel::GEAR, Color32::YELLOW),
+                    Series::from_profile(run, Channel::GEAR, Color32::YELLOW),
+                ],
+                Range::Fixed(-1.0, 7.0),
+                "Gear",
+            ),
+        ];
+        for (series, y_range, unit) in plots {
+            ui.horizontal(|ui| {
+                ui.add_space(PLOT_INSET);
+                Plot::new(COMPARE_PLOT_SIZE)
+                    .x_range(x_range)
+                    .y_range(y_range)
+                    .x_unit("m")
+                    .y_unit(unit)
+                    .show(ui, &series);
+            });
+        }
+        // how the gap built up over the visible stretch
+        let delta = history.distances().iter()
+            .zip(history.times())
+            .map(|(&d, &t)| [d, run.delta(d, t).unwrap_or(f32::NAN)])
+            .collect();
+        ui.horizontal(|ui| {
+            ui.add_space(PLOT_INSET);
+            Plot::new(COMPARE_PLOT_SIZE)
+                .x_range(x_range)
+                .x_unit("m")
+                .y_unit("s")
+                .show(ui, &[Series::new("Delta", "s", Color32::LIGHT_BLUE, delta)]);
+        });
+    });
+}
+
+
+fn damper_menu(
+    mut windows: Query<&mut Window>,
+    mut egui_ctx: EguiContexts,
+    mut next_state: ResMut<NextState<DisplayState>>,
+    rbr: Res<RBR>,
+    mut dampers: ResMut<Dampers>,
+    source: Res<State<SourceState>>,
+    mut replay: ResMut<Replay>,
+    link: Res<LinkHealth>,
+    mut settings: ResMut<Settings>,
+) {
+    let mut window = windows.single_mut();
+    window.resolution.set(DAMPER_VIEW_SIZE.x, DAMPER_VIEW_SIZE.y);
+    let gui = egui::Window::new("gui")
+        .title_bar(false)
+        .fixed_pos(ZERO)
+        .default_height(DAMPER_VIEW_SIZE.y)
+        .default_width(DAMPER_VIEW_SIZE.x)
+        .collapsible(false)
+        .frame(Frame {
+            fill: settings.background(),
+            inner_margin: Margin::same(0.0),
+            outer_margin: Margin::same(0.0),
+            ..default()
+        });
+    gui.show(egui_ctx.ctx_mut(), |ui| {
+        ui.set_width(DAMPER_VIEW_SIZE.x);
+        ui.style_mut()
+            .override_font_id = Some(FontId::new(
+                13.0,
+                 egui::FontFamily::Monospace
+        ));
+        ui.vertical_centered(|ui| {
+            ui.add_space(SPACING * 0.1);
+            let back = ui.button("Back");
+            if back.clicked() {
+                next_state.set(DisplayState::Main);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add_space(HORIZONTAL_CENTER);
+            match source.get() {
+                SourceState::Live => create_link_badge(ui, &link, rbr.stats.packets_per_second),
+                SourceState::Replay => create_replay_bar(ui, &mut replay),
+            }
+        });
+        let mut config = settings.dampers;
+        ui.horizontal(|ui| {
+            ui.add_space(BRAKE_VERTICAL_SPACING);
+            ui.selectable_value(&mut config.rolling, false, "Stage");
+            ui.selectable_value(&mut config.rolling, true, "Rolling");
+            if config.rolling {
+                ui.add(
+                    egui::DragValue::new(&mut config.window)
+                        .clamp_range(1.0..=600.0)
+                        .suffix("s")
+                );
+            }
+            ui.label("Knees");
+            ui.add(
+                egui::DragValue::new(&mut config.rebound_knee)
+                    .clamp_range(0.001..=1.0)
+                    .speed(0.001)
+                    .prefix("reb ")
+            );
+            ui.add(
+                egui::DragValue::new(&mut config.bump_knee)
+                    .clamp_range(0.001..=1.0)
+                    .speed(0.001)
+                    .prefix("bump ")
+            );
+        });
+        settings.dampers = config;
+        dampers.histogram.set_window(config.rolling.then_some(config.window));
+        ui.horizontal(|ui| {
+            ui.add_space(BRAKE_VERTICAL_SPACING);
+            if ui.button("Reset").clicked() {
+                dampers.histogram.clear();
+            }
+            if ui.button("Export CSV").clicked() {
+                dampers.export_csv();
+            }
+            ui.label(format!("{} samples", dampers.histogram.samples()));
+        });
+        match &dampers.export {
+            Some(Ok(path)) => {
+                ui.label(format!("Saved {}", path.display()));
+            },
+            Some(Err(e)) => {
+                ui.colored_label(Color32::RED, e);
+            },
+            None => {},
+        }
+        let histogram = &dampers.histogram;
+        ui.columns(2, |columns| {
+            create_histogram(&mut columns[0], "Front left", histogram, 0, config.bump_knee, config.rebound_knee);
+            create_histogram(&mut columns[1], "Front right", histogram, 1, config.bump_knee, config.rebound_knee);
+        });
+        ui.columns(2, |columns| {
+            create_histogram(&mut columns[0], "Rear left", histogram, 2, config.bump_knee, config.rebound_knee);
+            create_histogram(&mut columns[1], "Rear right", histogram, 3, config.bump_knee, config.rebound_knee);
+        });
+    });
+}
+
 fn tire_menu(
+    mut windows: Query<&mut Window>,
     mut egui_ctx: EguiContexts,
     mut next_state: ResMut<NextState<DisplayState>>,
-    rbr: Res<RBR>
+    rbr: Res<RBR>,
+    pressures: Res<Pressures>,
+    brakes: Res<Brakes>,
+    source: Res<State<SourceState>>,
+    mut replay: ResMut<Replay>,
+    link: Res<LinkHealth>,
+    mut settings: ResMut<Settings>,
 ) {
+    let car = rbr.telemetry.car.index;
+    let tire_gradient = settings.thresholds.tire_bands(settings.compound, car).gradient();
+    let brake_gradient = settings.thresholds.brake_bands(car).gradient();
+    // current pressure and the change since the start of the stage
+    let unit = settings.pressure_unit;
+    let current = tire_pressures(&rbr.telemetry);
+    let cold = pressures.cold();
+    let pressure = |corner: usize| match cold {
+        Some(cold) => format!("{} {}", unit.format(current[corner]), unit.format_delta(current[corner] - cold[corner])),
+        None => format!("{} {}", unit.format(current[corner]), unit.label()),
+    };
+    let fade = settings.brake_fade;
+    let fading: [bool; 4] = std::array::from_fn(|corner| brakes.hot_for(corner) > fade.time);
+    let mut window = windows.single_mut();
+    window.resolution.set(TIRE_VIEW_SIZE.x, TIRE_VIEW_SIZE.y);
     let gui = egui::Window::new("gui")
         .title_bar(false)
         .fixed_pos(ZERO)
-        .default_height(HEIGHT)
-        .default_width(WIDTH)
+        .default_height(TIRE_VIEW_SIZE.y)
+        .default_width(TIRE_VIEW_SIZE.x)
         .collapsible(false)
         .frame(Frame {
-            fill: MENU_BG,
+            fill: settings.background(),
             inner_margin: Margin::same(0.0),
             outer_margin: Margin::same(0.0),
             ..default()
@@ -282,43 +786,94 @@ fn tire_menu(
                 next_state.set(DisplayState::Main);
             }
         });
+        ui.horizontal(|ui| {
+            ui.add_space(HORIZONTAL_CENTER);
+            match source.get() {
+                SourceState::Live => create_link_badge(ui, &link, rbr.stats.packets_per_second),
+                SourceState::Replay => create_replay_bar(ui, &mut replay),
+            }
+            let mut compound = settings.compound;
+            egui::ComboBox::from_id_source("compound")
+                .selected_text(compound.label())
+                .show_ui(ui, |ui| {
+                    for c in Compound::ALL {
+                        ui.selectable_value(&mut compound, c, c.label());
+                    }
+                });
+            settings.compound = compound;
+            let mut unit = settings.pressure_unit;
+            egui::ComboBox::from_id_source("pressure unit")
+                .selected_text(unit.label())
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    for u in PressureUnit::ALL {
+                        ui.selectable_value(&mut unit, u, u.label());
+                    }
+                });
+            settings.pressure_unit = unit;
+        });
         ui.vertical(|ui| {
                 ui.add_space(VERTICAL_CENTER);
+                if let Some(cold) = cold {
+                    ui.horizontal(|ui| {
+                        ui.add_space(TIRE_HORIZONTAL_SPACING);
+                        ui.label(format!(
+                            "Cold {} {} / {} {}",
+                            unit.format(cold[0]),
+                            unit.format(cold[1]),
+                            unit.format(cold[2]),
+                            unit.format(cold[3])
+                        ));
+                    });
+                }
                 ui.vertical(|ui| {
                     ui.horizontal(|ui| {
                         ui.add_space(TIRE_HORIZONTAL_SPACING);
-                        let lf_brake = rbr.telemetry.car.suspension_lf.wheel.brake_disk.temperature;
-                        let lf_tire = rbr.telemetry.car.suspension_lf.wheel.tire.temperature;
-                        let rf_brake = rbr.telemetry.car.suspension_rf.wheel.brake_disk.temperature;
-                        let rf_tire = rbr.telemetry.car.suspension_rf.wheel.tire.temperature;
+                        let lf_brake = &rbr.telemetry.car.suspension_lf.wheel.brake_disk;
+                        let lf_tire = &rbr.telemetry.car.suspension_lf.wheel.tire;
+                        let rf_brake = &rbr.telemetry.car.suspension_rf.wheel.brake_disk;
+                        let rf_tire = &rbr.telemetry.car.suspension_rf.wheel.tire;
                         ui.vertical(|ui| {
                             ui.add_space(BRAKE_VERTICAL_SPACING);
-                            create_brake(ui, lf_brake);
+                            create_brake(ui, lf_brake, &brake_gradient, fading[0]);
                         });
                         ui.add_space(BRAKE_SPACING);
-                        create_tire(ui, lf_tire);
+                        create_tire(ui, lf_tire, &tire_gradient, &pressure(0));
                         ui.add_space(SPACING);
-                        create_tire(ui, rf_tire);
+                        create_tire(ui, rf_tire, &tire_gradient, &pressure(1));
                         ui.add_space(BRAKE_SPACING);
-                        create_brake(ui, rf_brake);
+                        create_brake(ui, rf_brake, &brake_gradient, fading[1]);
                     });
                     ui.add_space(SPACING);
                     ui.horizontal(|ui| {
                         ui.add_space(TIRE_HORIZONTAL_SPACING);
-                        let lb_brake = rbr.telemetry.car.suspension_lb.wheel.brake_disk.temperature;
-                        let lb_tire = rbr.telemetry.car.suspension_lb.wheel.tire.temperature;
-                        let rb_brake = rbr.telemetry.car.suspension_rb.wheel.brake_disk.temperature;
-                        let rb_tire = rbr.telemetry.car.suspension_rb.wheel.tire.temperature;
+                        let lb_brake = &rbr.telemetry.car.suspension_lb.wheel.brake_disk;
+                        let lb_tire = &rbr.telemetry.car.suspension_lb.wheel.tire;
+                        let rb_brake = &rbr.telemetry.car.suspension_rb.wheel.brake_disk;
+                        let rb_tire = &rbr.telemetry.car.suspension_rb.wheel.tire;
                         ui.vertical(|ui| {
                             ui.add_space(BRAKE_VERTICAL_SPACING);
-                            create_brake(ui, lb_brake);
+                            create_brake(ui, lb_brake, &brake_gradient, fading[2]);
                         });
                         ui.add_space(BRAKE_SPACING);
-                        create_tire(ui, lb_tire);
+                        create_tire(ui, lb_tire, &tire_gradient, &pressure(2));
                         ui.add_space(SPACING);
-                        create_tire(ui, rb_tire);
+                        create_tire(ui, rb_tire, &tire_gradient, &pressure(3));
                         ui.add_space(BRAKE_SPACING);
-                        create_brake(ui, rb_brake);
+                        create_brake(ui, rb_brake, &brake_gradient, fading[3]);
+                    });
+                    ui.add_space(BRAKE_VERTICAL_SPACING);
+                    ui.horizontal(|ui| {
+                        ui.add_space(TIRE_HORIZONTAL_SPACING);
+                        match brakes.bias() {
+                            Some(bias) => ui.label(format!("Bias {:.0}/{:.0}", bias * 100.0, (1.0 - bias) * 100.0)),
+                            None => ui.label("Bias --/--"),
+                        };
+                        for (corner, name) in CORNER_NAMES.iter().enumerate() {
+                            if fading[corner] {
+                                ui.colored_label(Color32::RED, format!("{name} fade"));
+                            }
+                        }
                     });
                 });
                 ui.add_space(VERTICAL_CENTER * 5.0);
@@ -334,8 +889,16 @@ fn main_menu(
     mut connection_state: ResMut<NextState<ConnectionState>>,
     connection_state_current: Res<State<ConnectionState>>,
     mut port: ResMut<Port>,
+    mut recorder: ResMut<Recorder>,
+    mut replay: ResMut<Replay>,
+    mut source: ResMut<NextState<SourceState>>,
+    source_current: Res<State<SourceState>>,
     socket: Res<Socket>,
-    rbr: Res<RBR>
+    rbr: Res<RBR>,
+    mut link: ResMut<LinkHealth>,
+    mut relay: ResMut<Relay>,
+    bests: Res<Bests>,
+    settings: Res<Settings>,
 ) {
     let mut window = windows.single_mut();
     window.resolution.set(WIDTH, HEIGHT);
@@ -346,7 +909,7 @@ fn main_menu(
         .default_width(WIDTH)
         .collapsible(false)
         .frame(Frame {
-            fill: MENU_BG,
+            fill: settings.background(),
             inner_margin: Margin::same(0.0),
             outer_margin: Margin::same(0.0),
             ..default()
@@ -357,6 +920,7 @@ fn main_menu(
                 20.0,
                  egui::FontFamily::Monospace
         ));
+        egui::ScrollArea::vertical().show(ui, |ui| {
         ui.vertical_centered(|ui| {
             ui.add_space(SPACING * 0.1);
             ui.set_height(HEIGHT);
@@ -368,36 +932,175 @@ fn main_menu(
             let pedals = ui.button("Pedal Telemetry");
             let tires = ui.button("Tire Telemetry");
             let suspension = ui.button("Suspension Telemetry");
+            let dampers = ui.button("Damper Histogram");
+            let engine = ui.button("Engine Telemetry");
+            let compare = ui.button("Compare Runs");
             
             ui.add_space(SPACING);
             let p = &socket.address;
             match connection_state_current.get() {
                 ConnectionState::Connected => {
-                    ui.colored_label(Color32::GREEN, format!("{p}"));
+                    ui.colored_label(Color32::GREEN, p);
+                    if *source_current.get() == SourceState::Live {
+                        create_link_badge(ui, &link, rbr.stats.packets_per_second);
+                    }
                 },
                 ConnectionState::Disconnected => {
-                    ui.label(
-                        format!("Waiting connection!")
-                    );
+                    ui.label("Waiting connection!");
                 }
             }
             
-            if rbr.recv {
+            if link.status != LinkStatus::Waiting || *source_current.get() == SourceState::Replay {
                 let time = rbr.telemetry.get_time();
                 
                 ui.label(format_time(time.minutes, time.seconds));
+                let telemetry = &rbr.telemetry;
+                match bests.runs.best(telemetry.stage.index, telemetry.car.index) {
+                    Some(best) => {
+                        let end = best.end_time();
+                        ui.label(format!("Best {}", format_time((end / 60.0).floor(), end % 60.0)));
+                        let delta = bests.runs.delta(telemetry);
+                        match delta {
+                            Some(delta) => {
+                                let color = if delta > 0.0 { Color32::RED } else { Color32::GREEN };
+                                ui.colored_label(color, format!("{delta:+.2}"));
+                            },
+                            None => {
+                                ui.label("--.--");
+                            },
+                        }
+                        create_delta_bar(ui, delta);
+                    },
+                    None => {
+                        ui.label(format!("No best for stage {} in car {} yet", telemetry.stage.index, telemetry.car.index));
+                    },
+                }
+            }
+            for e in &bests.errors {
+                ui.colored_label(Color32::YELLOW, e);
+            }
+            if let Some(layout) = rbr.protocol {
+                ui.label(format!("Protocol: {} v{}", layout.name, layout.version));
+                ui.label(format!(
+                    "{:.0} packets/s, {} skipped, {} stale",
+                    rbr.stats.packets_per_second,
+                    rbr.stats.skipped,
+                    rbr.stats.stale
+                ));
+            }
+            if let Some(e) = &settings.error {
+                ui.colored_label(Color32::YELLOW, e);
+            }
+            if let Some(e) = &rbr.stats.last_socket_error {
+                ui.colored_label(
+                    Color32::YELLOW,
+                    format!("Receive errors: {} ({e})", rbr.stats.socket_errors)
+                );
+            }
+            if rbr.rejected.total > 0 {
+                ui.colored_label(
+                    Color32::YELLOW,
+                    format!("Rejected packets: {}", rbr.rejected.total)
+                );
+                if let Some(e) = &rbr.rejected.last_error {
+                    ui.colored_label(Color32::YELLOW, e.to_string());
+                }
             }
             
             
 
+            let ip = ui.add(
+                egui::TextEdit::singleline(&mut port.ip)
+                .hint_text("Bind address, 0.0.0.0 for LAN")
+            );
             let response = ui.add(
                 egui::TextEdit::singleline(&mut port.port)
                 .hint_text("UDP port")
             );
             
-            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            if (response.lost_focus() || ip.lost_focus()) && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
                 connection_state.set(ConnectionState::Disconnected);
             }
+            ui.horizontal(|ui| {
+                ui.add_space(HORIZONTAL_CENTER);
+                ui.label("Timeout");
+                ui.add(
+                    egui::DragValue::new(&mut link.timeout)
+                        .clamp_range(1.0..=60.0)
+                        .speed(0.1)
+                        .suffix("s")
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.add_space(HORIZONTAL_CENTER);
+                let toggled = ui.checkbox(&mut relay.enabled, "Relay").changed();
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut relay.targets)
+                    .hint_text("6777, 192.168.1.5:6776")
+                );
+                if toggled || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+                    relay.apply(&port.ip, &port.port);
+                }
+            });
+            relay.poll();
+            if relay.is_resolving() {
+                ui.label("Resolving relay targets");
+            }
+            if relay.enabled && relay.target_count() > 0 {
+                ui.label(format!("Relayed {} packets to {} targets", relay.forwarded, relay.target_count()));
+            }
+            if let Some(e) = &relay.error {
+                ui.colored_label(Color32::YELLOW, e);
+            }
+
+            ui.horizontal(|ui| {
+                ui.add_space(HORIZONTAL_CENTER);
+                if recorder.is_recording() {
+                    if ui.button("Stop recording").clicked() {
+                        recorder.stop();
+                    }
+                } else if ui.button("Record").clicked() {
+                    recorder.start();
+                }
+                ui.checkbox(&mut recorder.auto_start, "Auto");
+            });
+            match recorder.state {
+                RecorderState::Armed => {
+                    ui.label("Waiting for telemetry");
+                },
+                RecorderState::Recording => {
+                    ui.colored_label(Color32::RED, format!("Recorded {} frames", recorder.frames));
+                },
+                RecorderState::Idle => {},
+            }
+            if let Some(e) = &recorder.error {
+                ui.colored_label(Color32::RED, e);
+            }
+
+            ui.add_space(SPACING * 0.5);
+            match source_current.get() {
+                SourceState::Live => {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut replay.path)
+                        .hint_text("Session file")
+                    );
+                    let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if (ui.button("Replay").clicked() || enter) && replay.load() {
+                        source.set(SourceState::Replay);
+                    }
+                },
+                SourceState::Replay => {
+                    create_replay_controls(ui, &mut replay);
+                    if ui.button("Back to live").clicked() {
+                        replay.playing = false;
+                        source.set(SourceState::Live);
+                    }
+                },
+            }
+            if let Some(e) = &replay.error {
+                ui.colored_label(Color32::RED, e);
+            }
             
 
             if pedals.clicked() {
@@ -409,9 +1112,19 @@ fn main_menu(
             if suspension.clicked() {
                 next_state.set(DisplayState::Suspension);
             }
+            if dampers.clicked() {
+                next_state.set(DisplayState::Dampers);
+            }
+            if engine.clicked() {
+                next_state.set(DisplayState::Engine);
+            }
+            if compare.clicked() {
+                next_state.set(DisplayState::Compare);
+            }
             
             
         });
+        });
             
     });
 }
\ No newline at end of file
@@ -0,0 +1,301 @@
+use egui::{Align2, Color32, FontId, Pos2, Rect, Sense, Shape, Stroke, Ui, Vec2};
+use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+// constants.rs
+use crate::constants::*;
+use rbr_telemetry::{Channel, ChannelHistory, RunProfile};
+
+// What channels are plotted against. Distance makes runs of the same stage line up
+// regardless of frame rate or time lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum XAxis {
+    Time,
+    Distance,
+    DistanceToEnd,
+}
+impl XAxis {
+    pub const ALL: [XAxis; 3] = [XAxis::Time, XAxis::Distance, XAxis::DistanceToEnd];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            XAxis::Time => "Time",
+            XAxis::Distance => "Distance",
+            XAxis::DistanceToEnd => "To finish",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            XAxis::Time => "s",
+            XAxis::Distance | XAxis::DistanceToEnd => "m",
+        }
+    }
+
+    pub fn values<'a>(&self, history: &'a ChannelHistory) -> &'a VecDeque<f32> {
+        match self {
+            XAxis::Time => history.times(),
+            XAxis::Distance => history.distances(),
+            XAxis::DistanceToEnd => history.values(Channel::DISTANCE_TO_END),
+        }
+    }
+
+    // The last `span` seconds or meters up to the newest frame. The distance to the
+    // finish counts down, so that axis runs from high to low.
+    pub fn scrolling(&self, history: &ChannelHistory, span: f32) -> Range {
+        let end = self.values(history).back().copied().unwrap_or(0.0);
+        match self {
+            XAxis::DistanceToEnd => Range::Fixed(end + span, end),
+            _ => Range::Fixed(end - span, end),
+        }
+    }
+}
+
+// One line of a plot, points are (x, y) in data units. NaN values break the line.
+pub struct Series {
+    pub name: String,
+    pub unit: &'static str,
+    pub color: Color32,
+    pub points: Vec<[f32; 2]>,
+}
+impl Series {
+    pub fn new(name: impl Into<String>, unit: &'static str, color: Color32, points: Vec<[f32; 2]>) -> Self {
+        Series {
+            name: name.into(),
+            unit,
+            color,
+            points,
+        }
+    }
+
+    pub fn from_history(history: &ChannelHistory, channel: Channel, axis: XAxis, color: Color32) -> Self {
+        let points = axis.values(history).iter()
+            .zip(history.values(channel))
+            .map(|(&t, &v)| [t, v])
+            .collect();
+        Series::new(channel.name(), channel.unit(), color, points)
+    }
+
+    // A channel of a reference run against stage distance, dimmed next to the live one.
+    pub fn from_profile(profile: &RunProfile, channel: Channel, color: Color32) -> Self {
+        let points = profile.values(channel)
+            .unwrap_or_default()
+            .iter()
+            .zip(profile.distances())
+            .map(|(&v, &d)| [d, v])
+            .collect();
+        Series::new(format!("{} ref", channel.name()), channel.unit(), color.gamma_multiply(REFERENCE_DIM), points)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Range {
+    Auto,
+    // left or bottom first, a reversed X axis runs from high to low
+    Fixed(f32, f32),
+}
+
+// Line graph with axes, gridlines, a legend and a readout of every series
+// under the pointer. Each series is drawn as one polyline.
+pub struct Plot {
+    size: Vec2,
+    x_range: Range,
+    y_range: Range,
+    x_unit: &'static str,
+    y_unit: &'static str,
+    legend: bool,
+}
+impl Plot {
+    pub fn new(size: Vec2) -> Self {
+        Plot {
+            size,
+            x_range: Range::Auto,
+            y_range: Range::Auto,
+            x_unit: "",
+            y_unit: "",
+            legend: true,
+        }
+    }
+
+    pub fn x_range(mut self, range: Range) -> Self {
+        self.x_range = range;
+        self
+    }
+
+    pub fn y_range(mut self, range: Range) -> Self {
+        self.y_range = range;
+        self
+    }
+
+    pub fn x_unit(mut self, unit: &'static str) -> Self {
+        self.x_unit = unit;
+        self
+    }
+
+    pub fn y_unit(mut self, unit: &'static str) -> Self {
+        self.y_unit = unit;
+        self
+    }
+
+    pub fn legend(mut self, legend: bool) -> Self {
+        self.legend = legend;
+        self
+    }
+
+    pub fn show(self, ui: &mut Ui, series: &[Series]) {
+        let (response, painter) = ui.allocate_painter(self.size, Sense::hover());
+        let outer = response.rect;
+        let rect = Rect::from_min_max(
+            outer.min + Vec2::new(PLOT_MARGIN.x, PLOT_MARGIN.y * 0.5),
+            outer.max - Vec2::new(PLOT_MARGIN.y * 0.5, PLOT_MARGIN.y),
+        );
+        let points = || series.iter().flat_map(|s| s.points.iter()).filter(|p| p[0].is_finite() && p[1].is_finite());
+        let (x_min, x_max) = resolve(self.x_range, points().map(|p| p[0]), false);
+        let (y_min, y_max) = resolve(self.y_range, points().map(|p| p[1]), true);
+        let to_screen = |x: f32, y: f32| Pos2::new(
+            rect.left() + (x - x_min) / (x_max - x_min) * rect.width(),
+            rect.bottom() - (y - y_min) / (y_max - y_min) * rect.height(),
+        );
+        let font = FontId::monospace(10.0);
+        let grid = Stroke::new(1.0, PLOT_GRID_COLOR);
+
+        for y in ticks(y_min, y_max, PLOT_Y_TICKS) {
+            let p = to_screen(x_min, y);
+            painter.line_segment([Pos2::new(rect.left(), p.y), Pos2::new(rect.right(), p.y)], grid);
+            painter.text(Pos2::new(rect.left() - 4.0, p.y), Align2::RIGHT_CENTER, format_tick(y), font.clone(), LINE_COLOR);
+        }
+        for x in ticks(x_min.min(x_max), x_min.max(x_max), PLOT_X_TICKS) {
+            let p = to_screen(x, y_min);
+            painter.line_segment([Pos2::new(p.x, rect.top()), Pos2::new(p.x, rect.bottom())], grid);
+            painter.text(Pos2::new(p.x, rect.bottom() + 2.0), Align2::CENTER_TOP, format_tick(x), font.clone(), LINE_COLOR);
+        }
+        painter.rect_stroke(rect, 0.0, Stroke::new(1.0, LINE_COLOR));
+        painter.text(outer.left_top(), Align2::LEFT_TOP, self.y_unit, font.clone(), LINE_COLOR);
+        painter.text(outer.right_bottom(), Align2::RIGHT_BOTTOM, self.x_unit, font.clone(), LINE_COLOR);
+
+        let clipped = painter.with_clip_rect(rect);
+        for s in series {
+            // NaN or a step back along the axis (a restart) starts a new line
+            let direction = (x_max - x_min).signum();
+            let mut line: Vec<Pos2> = Vec::with_capacity(s.points.len());
+            let mut last_x = f32::NEG_INFINITY * direction;
+            for &[x, y] in &s.points {
+                if !x.is_finite() || !y.is_finite() || (x - last_x) * direction < 0.0 {
+                    flush(&clipped, &mut line, s.color);
+                }
+                if x.is_finite() && y.is_finite() {
+                    line.push(to_screen(x, y));
+                    last_x = x;
+                }
+            }
+            flush(&clipped, &mut line, s.color);
+        }
+
+        if self.legend {
+            let mut pos = rect.left_top() + Vec2::new(4.0, 2.0);
+            for s in series {
+                let text = painter.text(pos, Align2::LEFT_TOP, &s.name, font.clone(), s.color);
+                pos.x = text.right() + 8.0;
+            }
+        }
+
+        let Some(pointer) = response.hover_pos().filter(|p| rect.contains(*p)) else {
+            return;
+        };
+        let x = x_min + (pointer.x - rect.left()) / rect.width() * (x_max - x_min);
+        painter.line_segment([Pos2::new(pointer.x, rect.top()), Pos2::new(pointer.x, rect.bottom())], Stroke::new(1.0, Color32::WHITE));
+        let mut lines = vec![(format!("{} {}", format_tick(x), self.x_unit), Color32::WHITE)];
+        for s in series {
+            if let Some(y) = nearest(&s.points, x) {
+                lines.push((format!("{} {y:.2} {}", s.name, s.unit), s.color));
+            }
+        }
+        // readout on the side away from the pointer
+        let right = pointer.x < rect.center().x;
+        let mut pos = Pos2::new(if right { pointer.x + 6.0 } else { pointer.x - 6.0 }, rect.top() + 14.0);
+        let align = if right { Align2::LEFT_TOP } else { Align2::RIGHT_TOP };
+        for (text, color) in lines {
+            let galley = painter.layout_no_wrap(text, font.clone(), color);
+            let text_rect = align.anchor_rect(Rect::from_min_size(pos, galley.size()));
+            painter.rect_filled(text_rect.expand(1.0), 0.0, MENU_BG);
+            painter.galley(text_rect.min, galley, color);
+            pos.y += 12.0;
+        }
+    }
+}
+
+fn flush(painter: &egui::Painter, line: &mut Vec<Pos2>, color: Color32) {
+    if line.len() > 1 {
+        painter.add(Shape::line(std::mem::take(line), Stroke::new(1.5, color)));
+    }
+    line.clear();
+}
+
+// Auto ranges cover the data, Y gets a little room so lines don't sit on the frame.
+fn resolve(range: Range, values: impl Iterator<Item = f32>, pad: bool) -> (f32, f32) {
+    let (min, max) = match range {
+        Range::Fixed(min, max) => return (min, if (max - min).abs() > 0.0 { max } else { min + 1.0 }),
+        Range::Auto => values.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v))),
+    };
+    if !min.is_finite() {
+        return (0.0, 1.0);
+    }
+    if max <= min {
+        return (min - 0.5, max + 0.5);
+    }
+    let padding = if pad { (max - min) * 0.05 } else { 0.0 };
+    (min - padding, max + padding)
+}
+
+// Round numbers between min and max, about `count` of them.
+fn ticks(min: f32, max: f32, count: usize) -> Vec<f32> {
+    let rough = (max - min) / count as f32;
+    let magnitude = 10f32.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(magnitude * 10.0);
+    let mut tick = (min / step).ceil() * step;
+    let mut ticks = Vec::new();
+    while tick <= max + step * 1e-3 {
+        ticks.push(tick);
+        tick += step;
+    }
+    ticks
+}
+
+fn format_tick(value: f32) -> String {
+    if value.abs() >= 100.0 || value.fract() == 0.0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+// Y of the point closest in x.
+fn nearest(points: &[[f32; 2]], x: f32) -> Option<f32> {
+    points.iter()
+        .filter(|p| p[1].is_finite())
+        .min_by(|a, b| (a[0] - x).abs().total_cmp(&(b[0] - x).abs()))
+        .map(|p| p[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_are_round_numbers() {
+        assert_eq!(ticks(0.0, 100.0, 5), vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0]);
+        assert_eq!(ticks(3.0, 17.0, 4), vec![5.0, 10.0, 15.0]);
+    }
+
+    #[test]
+    fn auto_range_covers_the_data() {
+        assert_eq!(resolve(Range::Auto, [2.0, 4.0].into_iter(), false), (2.0, 4.0));
+        assert_eq!(resolve(Range::Auto, [3.0].into_iter(), false), (2.5, 3.5));
+        assert_eq!(resolve(Range::Auto, std::iter::empty(), true), (0.0, 1.0));
+        assert_eq!(resolve(Range::Fixed(0.0, 100.0), [500.0].into_iter(), true), (0.0, 100.0));
+        assert_eq!(resolve(Range::Fixed(300.0, 100.0), std::iter::empty(), false), (300.0, 100.0));
+    }
+}
@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use rbr_telemetry::{Telemetry, SessionHeader, SessionFrame, write_header, write_frame, FINISH_DISTANCE};
+// constants.rs
+use crate::constants::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecorderState {
+    Idle,
+    // waiting for the first frame, the header needs the stage and car index
+    Armed,
+    Recording,
+}
+
+#[derive(Resource)]
+pub struct Recorder {
+    pub state: RecorderState,
+    pub auto_start: bool,
+    pub frames: u32,
+    pub path: Option<PathBuf>,
+    pub error: Option<String>,
+    writer: Option<BufWriter<File>>,
+    // file for the next recording instead of a generated name in RECORDING_DIR
+    target: Option<PathBuf>,
+    started: Instant,
+    auto_started: bool,
+    last_race_time: f32,
+}
+impl Recorder {
+    pub fn start(&mut self) {
+        self.stop();
+        self.state = RecorderState::Armed;
+        self.error = None;
+    }
+
+    pub fn start_to(&mut self, path: PathBuf) {
+        self.start();
+        self.target = Some(path);
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut writer) = self.writer.take() {
+            if let Err(e) = writer.flush() {
+                self.error = Some(e.to_string());
+            }
+        }
+        self.state = RecorderState::Idle;
+        self.auto_started = false;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.state != RecorderState::Idle
+    }
+
+    // Called for every decoded frame with the datagram it was decoded from.
+    pub fn record(&mut self, data: &[u8], telemetry: &Telemetry, layout_version: u16, received: Instant) {
+        let race_time = telemetry.stage.race_time;
+        // race time going backwards means the stage was restarted or a new one loaded
+        if self.auto_started && race_time < self.last_race_time {
+            self.stop();
+        }
+        if self.auto_start
+            && self.state == RecorderState::Idle
+            && self.last_race_time == 0.0
+            && race_time > 0.0
+        {
+            self.start();
+            self.auto_started = true;
+        }
+        self.last_race_time = race_time;
+
+        if self.state == RecorderState::Armed {
+            let header = SessionHeader::new(telemetry.stage.index, telemetry.car.index, layout_version);
+            if let Err(e) = self.open(&header) {
+                self.error = Some(e);
+                self.stop();
+                return;
+            }
+            self.started = received;
+            self.frames = 0;
+            self.state = RecorderState::Recording;
+        }
+
+        if let Some(writer) = self.writer.as_mut() {
+            let frame = SessionFrame {
+                time_us: received.saturating_duration_since(self.started).as_micros() as u64,
+                data: data.to_vec(),
+            };
+            match write_frame(writer, &frame) {
+                Ok(_) => self.frames += 1,
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    self.stop();
+                },
+            }
+        }
+        // an automatic recording is one run, it ends with the frame that crossed the finish
+        if self.auto_started && race_time > 0.0 && telemetry.stage.distance_to_end <= FINISH_DISTANCE {
+            self.stop();
+        }
+    }
+
+    fn open(&mut self, header: &SessionHeader) -> Result<(), String> {
+        let path = match self.target.take() {
+            Some(path) => path,
+            None => {
+                fs::create_dir_all(RECORDING_DIR).map_err(|e| e.to_string())?;
+                PathBuf::from(RECORDING_DIR).join(format!(
+                    "stage{}_car{}_{}.{RECORDING_EXTENSION}",
+                    header.stage_index,
+                    header.car_index,
+                    header.started_at
+                ))
+            },
+        };
+        let file = File::create(&path).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        write_header(&mut writer, header).map_err(|e| e.to_string())?;
+        self.writer = Some(writer);
+        self.path = Some(path);
+        Ok(())
+    }
+}
+impl Default for Recorder {
+    fn default() -> Self {
+        Recorder {
+            state: RecorderState::Idle,
+            auto_start: false,
+            frames: 0,
+            path: None,
+            error: None,
+            writer: None,
+            target: None,
+            started: Instant::now(),
+            auto_started: false,
+            last_race_time: 0.0,
+        }
+    }
+}
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbr_telemetry::Session;
+
+    fn frame(race_time: f32, distance_to_end: f32) -> Telemetry {
+        let mut telemetry = Telemetry::default();
+        telemetry.stage.index = 4;
+        telemetry.car.index = 2;
+        telemetry.stage.race_time = race_time;
+        telemetry.stage.distance_to_end = distance_to_end;
+        telemetry
+    }
+
+    fn auto_recorder(name: &str) -> (Recorder, PathBuf) {
+        let path = std::env::temp_dir().join(format!("rbr-recorder-{name}-{}.{RECORDING_EXTENSION}", std::process::id()));
+        let mut recorder = Recorder::default();
+        recorder.auto_start = true;
+        recorder.target = Some(path.clone());
+        (recorder, path)
+    }
+
+    fn record(recorder: &mut Recorder, telemetry: &Telemetry) {
+        recorder.record(&[telemetry.stage.race_time as u8], telemetry, 1, Instant::now());
+    }
+
+    #[test]
+    fn auto_start_waits_for_the_stage_to_start() {
+        let (mut recorder, path) = auto_recorder("start");
+        record(&mut recorder, &frame(0.0, 5000.0));
+        assert_eq!(recorder.state, RecorderState::Idle);
+        record(&mut recorder, &frame(0.1, 4999.0));
+        assert_eq!(recorder.state, RecorderState::Recording);
+        record(&mut recorder, &frame(0.2, 4998.0));
+        recorder.stop();
+
+        let session = Session::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(session.header.stage_index, 4);
+        assert_eq!(session.header.car_index, 2);
+        assert_eq!(session.frames.len(), 2);
+    }
+
+    #[test]
+    fn auto_recording_stops_at_the_finish() {
+        let (mut recorder, path) = auto_recorder("finish");
+        for (time, to_end) in [(0.0, 100.0), (1.0, 50.0), (2.0, 3.0), (2.0, 3.0)] {
+            record(&mut recorder, &frame(time, to_end));
+        }
+        assert_eq!(recorder.state, RecorderState::Idle);
+        assert_eq!(recorder.frames, 2);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn auto_recording_stops_on_restart() {
+        let (mut recorder, path) = auto_recorder("restart");
+        for time in [0.0, 1.0, 2.0] {
+            record(&mut recorder, &frame(time, 1000.0));
+        }
+        assert!(recorder.is_recording());
+        record(&mut recorder, &frame(0.0, 1000.0));
+        assert_eq!(recorder.state, RecorderState::Idle);
+        assert_eq!(recorder.frames, 2);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn manual_recording_is_not_stopped_automatically() {
+        let path = std::env::temp_dir().join(format!("rbr-recorder-manual-{}.{RECORDING_EXTENSION}", std::process::id()));
+        let mut recorder = Recorder::default();
+        recorder.start_to(path.clone());
+        for (time, to_end) in [(1.0, 10.0), (2.0, 0.0), (0.0, 1000.0)] {
+            record(&mut recorder, &frame(time, to_end));
+        }
+        assert_eq!(recorder.state, RecorderState::Recording);
+        assert_eq!(recorder.frames, 3);
+        recorder.stop();
+        fs::remove_file(&path).unwrap();
+    }
+}
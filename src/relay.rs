@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::thread::{self, JoinHandle};
+
+// constants.rs
+use crate::constants::*;
+
+// Resolved targets and why an entry was left out.
+type Resolved = (Vec<SocketAddr>, Option<String>);
+
+// Forwards every received datagram unchanged, so other tools can read the
+// stream the overlay is bound to.
+#[derive(Resource, Default)]
+pub struct Relay {
+    pub enabled: bool,
+    pub targets: String, // comma separated ports or host:port pairs
+    pub forwarded: u64,
+    pub error: Option<String>,
+    socket: Option<UdpSocket>,
+    addresses: Vec<SocketAddr>,
+    // host names can take seconds to resolve, so that happens off the UI thread
+    resolving: Option<JoinHandle<Resolved>>,
+    // targets, ip and port of the last apply
+    applied: Option<(String, String, String)>,
+}
+impl Relay {
+    // Resolves the target list in the background, an entry that is only a port is
+    // sent to localhost. `ip` and `port` are what the overlay listens on. Nothing
+    // happens when neither they nor the targets changed since the last call, unless
+    // the relay socket couldn't be bound then.
+    pub fn apply(&mut self, ip: &str, port: &str) {
+        let key = (self.targets.clone(), ip.to_string(), port.to_string());
+        if self.socket.is_some() && self.applied.as_ref() == Some(&key) {
+            return;
+        }
+        self.applied = Some(key);
+        self.addresses.clear();
+        self.error = None;
+        let targets = self.targets.clone();
+        let listen_ip = ip.trim().parse().unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let listen_port = port.trim().parse().ok();
+        self.resolving = Some(thread::spawn(move || resolve(&targets, listen_ip, listen_port)));
+        if self.socket.is_none() {
+            match UdpSocket::bind(RELAY_BIND) {
+                Ok(socket) => self.socket = Some(socket),
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+    }
+
+    // Picks up the resolved targets once they are ready, called every frame.
+    pub fn poll(&mut self) {
+        if !self.resolving.as_ref().is_some_and(|r| r.is_finished()) {
+            return;
+        }
+        let Some(resolving) = self.resolving.take() else {
+            return;
+        };
+        match resolving.join() {
+            Ok((addresses, error)) => {
+                self.addresses = addresses;
+                self.error = error.or(self.error.take());
+            },
+            Err(_) => self.error = Some("Resolving relay targets failed".to_string()),
+        }
+    }
+
+    pub fn is_resolving(&self) -> bool {
+        self.resolving.is_some()
+    }
+
+    pub fn target_count(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn forward(&mut self, data: &[u8]) {
+        if !self.enabled {
+            return;
+        }
+        let Some(socket) = &self.socket else {
+            return;
+        };
+        for address in &self.addresses {
+            // a tool that isn't running shouldn't stop the others from getting data
+            if socket.send_to(data, address).is_ok() {
+                self.forwarded += 1;
+            }
+        }
+    }
+}
+
+fn resolve(targets: &str, listen_ip: IpAddr, listen_port: Option<u16>) -> Resolved {
+    let mut addresses = Vec::new();
+    let mut error = None;
+    for target in targets.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let address = if target.parse::<u16>().is_ok() {
+            format!("{RELAY_IP}:{target}")
+        } else {
+            target.to_string()
+        };
+        match address.to_socket_addrs().map(|a| a.collect::<Vec<_>>()) {
+            Ok(resolved) if resolved.iter().any(|a| Some(a.port()) == listen_port && reaches(a.ip(), listen_ip)) => {
+                error = Some(format!("{target} is the port we listen on"));
+            },
+            Ok(resolved) if !resolved.is_empty() => addresses.push(resolved[0]),
+            _ => error = Some(format!("Invalid relay target: {target}")),
+        }
+    }
+    (addresses, error)
+}
+
+// Whether a datagram sent to `ip` arrives on a socket bound to `listen_ip`. Bound to
+// 0.0.0.0 that is any address of this machine, the ones we can bind to ourselves.
+fn reaches(ip: IpAddr, listen_ip: IpAddr) -> bool {
+    if !listen_ip.is_unspecified() {
+        return ip == listen_ip;
+    }
+    ip.is_loopback() || ip.is_unspecified() || UdpSocket::bind((ip, 0)).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(targets: &str, ip: &str, port: &str) -> Relay {
+        let mut relay = Relay {
+            targets: targets.to_string(),
+            ..Default::default()
+        };
+        relay.apply(ip, port);
+        while relay.is_resolving() {
+            thread::yield_now();
+            relay.poll();
+        }
+        relay
+    }
+
+    #[test]
+    fn targets_are_resolved_in_the_background() {
+        let relay = applied("6777, 127.0.0.1:6778", "127.0.0.1", "6776");
+        assert_eq!(relay.addresses, vec!["127.0.0.1:6777".parse().unwrap(), "127.0.0.1:6778".parse().unwrap()]);
+        assert_eq!(relay.error, None);
+
+        let relay = applied("6777, not a target", "0.0.0.0", "6776");
+        assert_eq!(relay.target_count(), 1);
+        assert_eq!(relay.error, Some("Invalid relay target: not a target".to_string()));
+    }
+
+    #[test]
+    fn the_listening_port_is_not_a_target() {
+        for target in ["6776", "127.0.0.1:6776", "localhost:6776", "0.0.0.0:6776"] {
+            let relay = applied(target, "0.0.0.0", "6776");
+            assert_eq!(relay.target_count(), 0, "{target}");
+            assert!(relay.error.is_some(), "{target}");
+        }
+        // bound to one address only, the same port elsewhere is another socket
+        let relay = applied("127.0.0.1:6776", "192.0.2.1", "6776");
+        assert_eq!(relay.target_count(), 1);
+        let relay = applied("192.0.2.1:6776", "192.0.2.1", "6776");
+        assert_eq!(relay.target_count(), 0);
+    }
+
+    #[test]
+    fn unchanged_targets_are_not_resolved_again() {
+        let mut relay = applied("6777", "127.0.0.1", "6776");
+        // connect_udp retrying the bind
+        relay.apply("127.0.0.1", "6776");
+        assert!(!relay.is_resolving());
+        assert_eq!(relay.target_count(), 1);
+
+        // another bind address or other targets are resolved again
+        relay.apply("127.0.0.1", "6777");
+        assert!(relay.is_resolving());
+        relay.targets = "6778".to_string();
+        relay.apply("127.0.0.1", "6777");
+        while relay.is_resolving() {
+            thread::yield_now();
+            relay.poll();
+        }
+        assert_eq!(relay.addresses, vec!["127.0.0.1:6778".parse().unwrap()]);
+    }
+}
+
@@ -0,0 +1,263 @@
+use bevy::prelude::*;
+use egui::Ui;
+use std::path::Path;
+
+use rbr_telemetry::{Session, format_time};
+// resources.rs
+use crate::resources::*;
+// settings.rs
+use crate::settings::Settings;
+
+pub const REPLAY_SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+#[derive(Resource)]
+pub struct Replay {
+    pub session: Option<Session>,
+    pub path: String,
+    pub playing: bool,
+    pub speed: f32,
+    pub position: f64, // seconds into the session
+    pub error: Option<String>,
+    cursor: Option<usize>,
+}
+impl Replay {
+    pub fn load(&mut self) -> bool {
+        match Session::load(Path::new(self.path.trim())) {
+            Ok(session) => {
+                self.session = Some(session);
+                self.position = 0.0;
+                self.cursor = None;
+                self.playing = true;
+                self.error = None;
+                true
+            },
+            Err(e) => {
+                self.error = Some(e);
+                false
+            },
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.session.as_ref().map_or(0.0, |s| s.duration())
+    }
+
+    pub fn seek(&mut self, position: f64) {
+        self.position = position.clamp(0.0, self.duration());
+        // force the frame at the new position to be shown even while paused
+        self.cursor = None;
+    }
+
+    pub fn toggle(&mut self) {
+        if !self.playing && self.position >= self.duration() {
+            self.seek(0.0);
+        }
+        self.playing = !self.playing;
+    }
+}
+impl Default for Replay {
+    fn default() -> Self {
+        Replay {
+            session: None,
+            path: String::new(),
+            playing: false,
+            speed: 1.0,
+            position: 0.0,
+            error: None,
+            cursor: None,
+        }
+    }
+}
+
+pub fn replay_handler(
+    time: Res<Time>,
+    mut replay: ResMut<Replay>,
+    mut rbr: ResMut<RBR>,
+    mut history: ResMut<History>,
+    mut inputs: ResMut<InputHistory>,
+    mut dampers: ResMut<Dampers>,
+    mut pressures: ResMut<Pressures>,
+    mut brakes: ResMut<Brakes>,
+    mut engine: ResMut<EngineHistory>,
+    settings: Res<Settings>,
+) {
+    let duration = replay.duration();
+    if replay.playing {
+        replay.position += time.delta_seconds_f64() * replay.speed as f64;
+        if replay.position >= duration {
+            replay.position = duration;
+            replay.playing = false;
+        }
+    }
+    let replay = &mut *replay;
+    let Some(session) = replay.session.as_ref() else {
+        return;
+    };
+    if session.frames.is_empty() {
+        return;
+    }
+    let index = session.frame_at(replay.position);
+    if replay.cursor == Some(index) {
+        return;
+    }
+    // every frame played since the last update goes into the history, a seek only shows the target
+    let first = match replay.cursor {
+        Some(cursor) if cursor < index => cursor + 1,
+        _ => index,
+    };
+    replay.cursor = Some(index);
+    for frame in &session.frames[first..=index] {
+        if let Ok(telemetry) = rbr.decode(&frame.data) {
+            history.add(&telemetry);
+            inputs.add(frame.time_us as f32 / 1e6, &telemetry);
+            dampers.histogram.add_car(telemetry.stage.race_time, &telemetry.car);
+            pressures.add(&telemetry);
+            brakes.add(&telemetry, settings.brake_fade.temperature);
+            engine.add(&telemetry);
+            rbr.telemetry = telemetry;
+        }
+    }
+}
+
+pub fn create_replay_bar(
+    ui: &mut Ui,
+    replay: &mut Replay,
+) {
+    ui.horizontal(|ui| {
+        let label = if replay.playing { "Pause" } else { "Play" };
+        if ui.button(label).clicked() {
+            replay.toggle();
+        }
+        let position = replay.position as f32;
+        let duration = replay.duration() as f32;
+        ui.label(format!(
+            "{}/{}",
+            format_time((position / 60.0).floor(), (position % 60.0).floor()),
+            format_time((duration / 60.0).floor(), (duration % 60.0).floor())
+        ));
+        let mut speed = replay.speed;
+        egui::ComboBox::from_id_source("replay speed")
+            .selected_text(format!("{speed}x"))
+            .width(60.0)
+            .show_ui(ui, |ui| {
+                for s in REPLAY_SPEEDS {
+                    ui.selectable_value(&mut speed, s, format!("{s}x"));
+                }
+            });
+        replay.speed = speed;
+        let mut position = replay.position;
+        let slider = ui.add(
+            egui::Slider::new(&mut position, 0.0..=replay.duration())
+                .show_value(false)
+        );
+        if slider.changed() {
+            replay.seek(position);
+        }
+    });
+}
+
+pub fn create_replay_controls(
+    ui: &mut Ui,
+    replay: &mut Replay,
+) {
+    if let Some(session) = &replay.session {
+        ui.label(format!(
+            "Stage {}, car {}",
+            session.header.stage_index,
+            session.header.car_index
+        ));
+    }
+    create_replay_bar(ui, replay);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::{BufWriter, Write};
+    use rbr_telemetry::{Channel, Telemetry, SessionHeader, SessionFrame, TELEMETRY_DATA_LAYOUT, write_header, write_frame};
+
+    const FRAMES: u32 = 50;
+
+    // A second of driving at 50 frames per second with the throttle coming up.
+    fn write_fixture(path: &Path) {
+        let mut writer = BufWriter::new(File::create(path).unwrap());
+        write_header(&mut writer, &SessionHeader::new(3, 7, TELEMETRY_DATA_LAYOUT.version)).unwrap();
+        for i in 0..FRAMES {
+            let mut telemetry = Telemetry::default();
+            telemetry.stage.index = 3;
+            telemetry.car.index = 7;
+            telemetry.stage.race_time = 1.0 + i as f32 * 0.02;
+            telemetry.stage.progress = i as f32;
+            telemetry.stage.distance_to_end = 1000.0 - i as f32;
+            telemetry.control.throttle = i as f32 / FRAMES as f32;
+            telemetry.control.gear = 2;
+            let frame = SessionFrame {
+                time_us: i as u64 * 20_000,
+                data: TELEMETRY_DATA_LAYOUT.encode(&telemetry),
+            };
+            write_frame(&mut writer, &frame).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    fn replay_app(path: &Path) -> App {
+        let mut replay = Replay {
+            path: path.display().to_string(),
+            ..Default::default()
+        };
+        assert!(replay.load());
+        replay.playing = false;
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(replay)
+            .init_resource::<RBR>()
+            .init_resource::<History>()
+            .init_resource::<InputHistory>()
+            .init_resource::<Dampers>()
+            .init_resource::<Pressures>()
+            .init_resource::<Brakes>()
+            .init_resource::<EngineHistory>()
+            .init_resource::<Settings>()
+            .add_systems(Update, replay_handler);
+        app
+    }
+
+    #[test]
+    fn views_follow_a_replayed_session() {
+        let path = std::env::temp_dir().join(format!("rbr-replay-view-{}.rbrs", std::process::id()));
+        write_fixture(&path);
+        let mut app = replay_app(&path);
+        fs::remove_file(&path).unwrap();
+
+        app.update();
+        let rbr = app.world.resource::<RBR>();
+        assert_eq!(rbr.telemetry.stage.index, 3);
+        assert_eq!(rbr.telemetry.car.index, 7);
+        assert_eq!(rbr.telemetry.stage.race_time, 1.0);
+        assert_eq!(rbr.telemetry.control.gear, 1);
+        assert_eq!(app.world.resource::<History>().len(), 1);
+
+        // seeking shows the frame at the new position
+        app.world.resource_mut::<Replay>().seek(0.5);
+        app.update();
+        let rbr = app.world.resource::<RBR>();
+        assert_eq!(rbr.telemetry.stage.progress, 25.0);
+        assert_eq!(rbr.telemetry.control.throttle, 50.0);
+        let history = app.world.resource::<History>();
+        assert_eq!(history.latest(Channel::THROTTLE), Some(50.0));
+
+        // stepping through while paused plays every frame in between into the history
+        let before = history.len();
+        app.world.resource_mut::<Replay>().position = 0.6;
+        app.update();
+        assert_eq!(app.world.resource::<History>().len(), before + 5);
+
+        // seeking back restarts the graphs like a restarted stage would
+        app.world.resource_mut::<Replay>().seek(0.0);
+        app.update();
+        assert_eq!(app.world.resource::<RBR>().telemetry.stage.race_time, 1.0);
+        assert_eq!(app.world.resource::<History>().len(), 1);
+    }
+}
@@ -1,33 +1,177 @@
-#[allow(dead_code)]
-
 use bevy::prelude::*;
 use std::net::UdpSocket;
 use std::io::Error;
-use bincode::deserialize;
-use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
 
-// telemetry.rs
-use crate::Telemetry;
-use crate::Control;
+use rbr_telemetry::{Telemetry, DecodeError, RejectedPackets, ChannelHistory, DamperHistogram, StagePressures, BrakeMonitor, EngineTrend, Layout, RunProfile, Session, PersonalBests, encode_best, decode_best};
 // constants.rs
 use crate::constants::*;
 
-#[derive(Resource)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Resource, Default)]
 pub struct RBR {
     pub telemetry: Telemetry,
-    pub recv: bool,
+    pub rejected: RejectedPackets,
+    pub protocol: Option<&'static Layout>,
+    pub stats: PacketStats,
 }
 impl RBR {
-    pub fn get_data(&mut self, data: &[u8]) {
-        self.telemetry = deserialize(&data).unwrap();
-        self.telemetry.format();
+    // Decodes without replacing the displayed frame, so on a bad packet the last good one is kept.
+    pub fn decode(&mut self, data: &[u8]) -> Result<Telemetry, DecodeError> {
+        match Telemetry::decode_layout(data) {
+            Ok((telemetry, layout)) => {
+                self.protocol = Some(layout);
+                Ok(telemetry)
+            },
+            Err(e) => {
+                self.rejected.add(&e);
+                Err(e)
+            },
+        }
+    }
+
+    // A packet that arrived after a newer one, e.g. reordered on the network.
+    pub fn is_stale(&self, telemetry: &Telemetry) -> bool {
+        let last = self.telemetry.total_steps;
+        let steps = telemetry.total_steps;
+        // a big jump back is a restarted stage rather than a late packet
+        steps < last && last - steps < STALE_STEP_WINDOW
+    }
+}
+
+pub struct PacketStats {
+    pub received: u64,
+    // decoded but replaced by a newer packet before it was displayed
+    pub skipped: u64,
+    pub stale: u64,
+    // receive errors other than the socket having nothing queued
+    pub socket_errors: u64,
+    pub last_socket_error: Option<String>,
+    pub packets_per_second: f32,
+    window_start: Instant,
+    window_packets: u32,
+}
+impl PacketStats {
+    pub fn add(&mut self, now: Instant) {
+        self.received += 1;
+        self.window_packets += 1;
+        self.update(now);
+    }
+
+    // Logged once per distinct error, a broken socket fails every frame.
+    pub fn socket_error(&mut self, error: &Error) {
+        self.socket_errors += 1;
+        let message = error.to_string();
+        if self.last_socket_error.as_ref() != Some(&message) {
+            warn!("UDP receive failed: {message}");
+            self.last_socket_error = Some(message);
+        }
+    }
+
+    pub fn update(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= Duration::from_secs(1) {
+            self.packets_per_second = self.window_packets as f32 / elapsed.as_secs_f32();
+            self.window_packets = 0;
+            self.window_start = now;
+        }
+    }
+}
+impl Default for PacketStats {
+    fn default() -> Self {
+        PacketStats {
+            received: 0,
+            skipped: 0,
+            stale: 0,
+            socket_errors: 0,
+            last_socket_error: None,
+            packets_per_second: 0.0,
+            window_start: Instant::now(),
+            window_packets: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStatus {
+    // nothing received yet, or nothing for longer than the timeout
+    Waiting,
+    Live,
+    // packets keep coming but the simulation doesn't advance, e.g. pause menu
+    Paused,
+    // packets stopped recently, the plugin or the game stopped sending
+    NotSending,
+}
+impl LinkStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LinkStatus::Waiting => "Waiting",
+            LinkStatus::Live => "Live",
+            LinkStatus::Paused => "Paused",
+            LinkStatus::NotSending => "Not sending",
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct LinkHealth {
+    pub status: LinkStatus,
+    pub timeout: f32, // seconds without packets before going back to waiting
+    last_packet: Option<Instant>,
+    last_step: Option<Instant>, // when the simulation last advanced
+    position: (f32, f32, u32), // race time, progress and total steps of the last packet
+}
+impl LinkHealth {
+    // The documented layout has no step counter, so race time and progress
+    // moving count as the simulation advancing too.
+    pub fn packet(&mut self, telemetry: &Telemetry, now: Instant) {
+        self.last_packet = Some(now);
+        let position = (telemetry.stage.race_time, telemetry.stage.progress, telemetry.total_steps);
+        if self.last_step.is_none() || position != self.position {
+            self.position = position;
+            self.last_step = Some(now);
+        }
+    }
+
+    pub fn update(&mut self, now: Instant) {
+        let (Some(packet), Some(step)) = (self.last_packet, self.last_step) else {
+            self.status = LinkStatus::Waiting;
+            return;
+        };
+        let since_packet = now.saturating_duration_since(packet).as_secs_f32();
+        let since_step = now.saturating_duration_since(step).as_secs_f32();
+        self.status = if since_packet > self.timeout {
+            LinkStatus::Waiting
+        } else if since_packet > LINK_STALL_TIME {
+            LinkStatus::NotSending
+        } else if since_step > LINK_PAUSE_TIME {
+            LinkStatus::Paused
+        } else {
+            LinkStatus::Live
+        };
+    }
+
+    pub fn since_last_packet(&self, now: Instant) -> Option<Duration> {
+        self.last_packet.map(|packet| now.saturating_duration_since(packet))
+    }
+
+    pub fn reset(&mut self) {
+        self.status = LinkStatus::Waiting;
+        self.last_packet = None;
+        self.last_step = None;
     }
 }
-impl Default for RBR {
+impl Default for LinkHealth {
     fn default() -> Self {
-        RBR {
-            telemetry: Telemetry::default(),
-            recv: false,
+        LinkHealth {
+            status: LinkStatus::Waiting,
+            timeout: LINK_TIMEOUT,
+            last_packet: None,
+            last_step: None,
+            position: (0.0, 0.0, 0),
         }
     }
 }
@@ -38,9 +182,11 @@ pub struct Socket {
     pub address: String,
 }
 impl Socket {
-    pub fn bind(&mut self, port: &str) {
-        self.address = format!("{UDP_IP}{port}");
-        self.socket = UdpSocket::bind(&self.address);
+    // 0.0.0.0 accepts telemetry sent from another machine on the network.
+    pub fn bind(&mut self, ip: &str, port: &str) {
+        self.address = format!("{}:{port}", ip.trim());
+        self.socket = UdpSocket::bind(&self.address)
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
     }
 }
 impl Default for Socket {
@@ -54,11 +200,13 @@ impl Default for Socket {
 
 #[derive(Resource)]
 pub struct Port {
+    pub ip: String,
     pub port: String,
 }
 impl Default for Port {
     fn default() -> Self {
         Port {
+            ip: UDP_IP.to_string(),
             port: String::new(),
         }
     }
@@ -70,50 +218,200 @@ pub enum ConnectionState {
     Connected,
 }
 
+// Where telemetry frames come from.
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SourceState {
+    Live,
+    Replay,
+}
+
+#[derive(States, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum DisplayState {
     Main,
     Tires,
     Pedals,
-    Suspension
+    Suspension,
+    Dampers,
+    Engine,
+    Compare,
+}
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct History(pub ChannelHistory);
+impl Default for History {
+    fn default() -> Self {
+        History(ChannelHistory::new(HISTORY_CAPACITY, HISTORY_DURATION))
+    }
 }
 
+// The inputs against the clock packets arrive on, for the pedal view while race
+// time stands still: before the start, in the pause menu and after the finish.
 #[derive(Resource)]
-pub struct Pedals {
-    pub throttle: VecDeque<f32>,
-    pub brake: VecDeque<f32>,
-    pub handbrake: VecDeque<f32>,
-    pub clutch: VecDeque<f32>,
-    pub gear: VecDeque<i32>,
-    pub size: u32,
-}
-impl Pedals {
-    pub fn add_data(&mut self, data: &Control) {
-        if self.size > ((GRAPH_SIZE.x) as u32) {
-            self.throttle.pop_front();
-            self.brake.pop_front();
-            self.handbrake.pop_front();
-            self.clutch.pop_front();
-            self.gear.pop_front();
-        } else {
-            self.size += 1;
+pub struct InputHistory {
+    pub history: ChannelHistory,
+    started: Instant,
+    race_time: Option<f32>,
+    // clock time the race time last changed
+    changed: f32,
+}
+impl Default for InputHistory {
+    fn default() -> Self {
+        InputHistory {
+            history: ChannelHistory::new(HISTORY_CAPACITY, PEDAL_GRAPH_SPAN),
+            started: Instant::now(),
+            race_time: None,
+            changed: 0.0,
+        }
+    }
+}
+impl InputHistory {
+    // `time` in seconds on any clock that only goes backwards on a seek.
+    pub fn add(&mut self, time: f32, telemetry: &Telemetry) {
+        let race_time = telemetry.stage.race_time;
+        if self.race_time != Some(race_time) || self.history.times().back().is_some_and(|&t| time < t) {
+            self.race_time = Some(race_time);
+            self.changed = time;
         }
-        self.throttle.push_back(data.throttle);
-        self.brake.push_back(data.brake);
-        self.handbrake.push_back(data.handbrake);
-        self.clutch.push_back(data.clutch);
-        self.gear.push_back(data.gear);
+        self.history.add_at(time, telemetry);
+    }
+
+    pub fn add_received(&mut self, received: Instant, telemetry: &Telemetry) {
+        let time = received.saturating_duration_since(self.started).as_secs_f32();
+        self.add(time, telemetry);
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.history.times().back().is_some_and(|&t| t - self.changed > LINK_PAUSE_TIME)
     }
 }
-impl Default for Pedals {
+
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct Pressures(pub StagePressures);
+
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct Brakes(pub BrakeMonitor);
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct EngineHistory(pub EngineTrend);
+impl Default for EngineHistory {
     fn default() -> Self {
-        Pedals {
-            throttle: VecDeque::new(),
-            brake: VecDeque::new(),
-            clutch: VecDeque::new(),
-            gear: VecDeque::new(),
-            handbrake: VecDeque::new(),
-            size: 0,
+        EngineHistory(EngineTrend::new(ENGINE_TREND_WINDOW))
+    }
+}
+
+// A run loaded from a recorded session to compare against.
+#[derive(Resource, Default)]
+pub struct Reference {
+    pub path: String,
+    pub run: Option<RunProfile>,
+    pub error: Option<String>,
+    // stage and car index the run was picked for, None for any
+    picked_for: Option<(i32, i32)>,
+}
+impl Reference {
+    // Takes the best run on this (stage, car), or on any when None.
+    pub fn load(&mut self, on: Option<(i32, i32)>) -> bool {
+        self.picked_for = on;
+        let run = Session::load(Path::new(self.path.trim()))
+            .and_then(|session| {
+                RunProfile::from_session(&session, &REFERENCE_CHANNELS, on)
+                    .ok_or(match on {
+                        Some((stage, car)) => format!("no run on stage {stage} in car {car} in this session"),
+                        None => "no run in this session".to_string(),
+                    })
+            });
+        match run {
+            Ok(run) => {
+                self.run = Some(run);
+                self.error = None;
+                true
+            },
+            Err(e) => {
+                self.error = Some(e);
+                false
+            },
+        }
+    }
+
+    // Picks the run of the session again once the live stage or car isn't the one it
+    // was picked for, e.g. loaded from the command line before any packet arrived.
+    pub fn follow(&mut self, live: Option<(i32, i32)>) {
+        if live.is_some() && live != self.picked_for && !self.path.trim().is_empty() {
+            self.load(live);
+        }
+    }
+}
+
+// Personal best per stage and car, kept as one file each in BESTS_DIR.
+#[derive(Resource, Default)]
+pub struct Bests {
+    pub runs: PersonalBests,
+    // why bests couldn't be read or saved
+    pub errors: Vec<String>,
+}
+impl Bests {
+    pub fn load() -> Bests {
+        let mut bests = Bests::default();
+        let Ok(entries) = fs::read_dir(BESTS_DIR) else {
+            return bests;
+        };
+        let paths = entries.flatten()
+            .map(|e| e.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == BEST_EXTENSION));
+        for path in paths {
+            let run = fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| decode_best(&data));
+            match run {
+                Ok(run) => bests.runs.insert(run),
+                Err(e) => bests.errors.push(format!("Couldn't read {}: {e}", path.display())),
+            }
+        }
+        bests
+    }
+
+    // Saves the run right away when it's a new best.
+    pub fn add(&mut self, telemetry: &Telemetry) {
+        let Some(run) = self.runs.add(telemetry) else {
+            return;
+        };
+        let path = PathBuf::from(BESTS_DIR)
+            .join(format!("stage_{}_car_{}.{BEST_EXTENSION}", run.stage_index, run.car_index));
+        let result = encode_best(run)
+            .map_err(|e| e.to_string())
+            .and_then(|data| {
+                fs::create_dir_all(BESTS_DIR)
+                    .and_then(|_| fs::write(&path, data))
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            self.errors.push(format!("Couldn't save best run: {e}"));
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Dampers {
+    pub histogram: DamperHistogram,
+    // last CSV export, the file or why it failed
+    pub export: Option<Result<PathBuf, String>>,
+}
+impl Dampers {
+    pub fn export_csv(&mut self) {
+        let unix = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let path = PathBuf::from(EXPORT_DIR).join(format!("dampers_{unix}.csv"));
+        let result = fs::create_dir_all(EXPORT_DIR)
+            .and_then(|_| fs::write(&path, self.histogram.to_csv()))
+            .map(|_| path)
+            .map_err(|e| e.to_string());
+        self.export = Some(result);
+    }
+}
+impl Default for Dampers {
+    fn default() -> Self {
+        Dampers {
+            histogram: DamperHistogram::new(DAMPER_BIN_WIDTH, DAMPER_BIN_COUNT),
+            export: None,
         }
     }
 }
@@ -138,3 +436,164 @@ impl Default for PedalCheckboxes {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbr_telemetry::{Channel, SessionFrame, SessionHeader, TELEMETRY_DATA_LAYOUT, write_frame, write_header};
+
+    #[test]
+    fn bad_packets_keep_the_last_good_frame() {
+        let mut rbr = RBR::default();
+        let mut raw = Telemetry::default();
+        raw.stage.race_time = 12.5;
+        raw.control.throttle = 0.5;
+        rbr.telemetry = rbr.decode(&TELEMETRY_DATA_LAYOUT.encode(&raw)).unwrap();
+
+        raw.stage.race_time = 13.0;
+        raw.control.throttle = f32::NAN;
+        assert!(rbr.decode(&TELEMETRY_DATA_LAYOUT.encode(&raw)).is_err());
+        assert!(rbr.decode(&[0; 100]).is_err());
+
+        assert_eq!(rbr.telemetry.stage.race_time, 12.5);
+        assert_eq!(rbr.telemetry.control.throttle, 50.0);
+        assert_eq!(rbr.rejected.non_finite, 1);
+        assert_eq!(rbr.rejected.unknown_layout, 1);
+        assert_eq!(rbr.rejected.total, 2);
+        assert_eq!(rbr.protocol.map(|layout| layout.version), Some(TELEMETRY_DATA_LAYOUT.version));
+    }
+
+    #[test]
+    fn socket_errors_are_counted() {
+        let mut stats = PacketStats::default();
+        let refused = Error::from(std::io::ErrorKind::ConnectionRefused);
+        stats.socket_error(&refused);
+        stats.socket_error(&refused);
+        assert_eq!(stats.socket_errors, 2);
+        assert_eq!(stats.last_socket_error, Some(refused.to_string()));
+    }
+
+    fn moving(race_time: f32) -> Telemetry {
+        let mut telemetry = Telemetry::default();
+        telemetry.stage.race_time = race_time;
+        telemetry.stage.progress = race_time * 10.0;
+        telemetry
+    }
+
+    #[test]
+    fn link_status_follows_the_packets() {
+        let start = Instant::now();
+        let at = |seconds: f32| start + Duration::from_secs_f32(seconds);
+        let mut link = LinkHealth::default();
+        link.update(start);
+        assert_eq!(link.status, LinkStatus::Waiting);
+
+        // no step counter, like the documented layout
+        for i in 0..10 {
+            link.packet(&moving(i as f32 * 0.1), at(i as f32 * 0.1));
+        }
+        link.update(at(0.95));
+        assert_eq!(link.status, LinkStatus::Live);
+
+        // packets keep coming with the race time frozen
+        for i in 10..30 {
+            link.packet(&moving(0.9), at(i as f32 * 0.1));
+        }
+        link.update(at(2.95));
+        assert_eq!(link.status, LinkStatus::Paused);
+
+        link.packet(&moving(1.0), at(3.0));
+        link.update(at(3.05));
+        assert_eq!(link.status, LinkStatus::Live);
+
+        link.update(at(3.0 + LINK_STALL_TIME + 0.1));
+        assert_eq!(link.status, LinkStatus::NotSending);
+        link.update(at(3.0 + LINK_TIMEOUT + 0.1));
+        assert_eq!(link.status, LinkStatus::Waiting);
+
+        link.packet(&moving(1.1), at(10.0));
+        link.reset();
+        link.update(at(10.0));
+        assert_eq!(link.status, LinkStatus::Waiting);
+    }
+
+    #[test]
+    fn link_is_live_while_steps_advance_before_the_start() {
+        let start = Instant::now();
+        let mut link = LinkHealth::default();
+        for step in 0..20 {
+            let telemetry = Telemetry {
+                total_steps: step,
+                ..Default::default()
+            };
+            link.packet(&telemetry, start + Duration::from_millis(step as u64 * 100));
+        }
+        link.update(start + Duration::from_millis(1950));
+        assert_eq!(link.status, LinkStatus::Live);
+    }
+
+    #[test]
+    fn inputs_follow_the_clock_while_race_time_stands_still() {
+        let mut inputs = InputHistory::default();
+        let mut telemetry = Telemetry::default();
+        // racing: race time advances with the clock
+        for i in 0..10 {
+            telemetry.stage.race_time = i as f32 * 0.1;
+            inputs.add(i as f32 * 0.1, &telemetry);
+        }
+        assert!(!inputs.is_frozen());
+        // paused: race time stops, the throttle is still blipped
+        for i in 10..30 {
+            telemetry.control.throttle = i as f32;
+            inputs.add(i as f32 * 0.1, &telemetry);
+        }
+        assert!(inputs.is_frozen());
+        assert_eq!(inputs.history.len(), 30);
+        assert_eq!(inputs.history.latest(Channel::THROTTLE), Some(29.0));
+        // resumed
+        telemetry.stage.race_time = 1.0;
+        inputs.add(3.0, &telemetry);
+        assert!(!inputs.is_frozen());
+    }
+
+    #[test]
+    fn reference_follows_the_live_stage_and_car() {
+        // stage 4 driven in car 0, then further in car 2
+        let path = std::env::temp_dir().join(format!("rbr-reference-{}.rbrs", std::process::id()));
+        let mut data = Vec::new();
+        write_header(&mut data, &SessionHeader::new(4, 0, TELEMETRY_DATA_LAYOUT.version)).unwrap();
+        for (i, (car, progress)) in [(0, 10.0), (0, 20.0), (2, 10.0), (2, 50.0)].into_iter().enumerate() {
+            let mut telemetry = Telemetry::default();
+            telemetry.stage.index = 4;
+            telemetry.car.index = car;
+            telemetry.stage.race_time = progress / 10.0;
+            telemetry.stage.progress = progress;
+            let frame = SessionFrame {
+                time_us: i as u64 * 20_000,
+                data: TELEMETRY_DATA_LAYOUT.encode(&telemetry),
+            };
+            write_frame(&mut data, &frame).unwrap();
+        }
+        fs::write(&path, data).unwrap();
+
+        let mut reference = Reference {
+            path: path.display().to_string(),
+            ..Default::default()
+        };
+        // from the command line, before any packet
+        assert!(reference.load(None));
+        assert_eq!(reference.run.as_ref().unwrap().car_index, 2);
+        reference.follow(None);
+        assert_eq!(reference.run.as_ref().unwrap().car_index, 2);
+        reference.follow(Some((4, 0)));
+        assert_eq!(reference.run.as_ref().unwrap().car_index, 0);
+        assert_eq!(reference.run.as_ref().unwrap().end_distance(), 20.0);
+
+        // picked once per stage and car, not on every frame
+        fs::remove_file(&path).unwrap();
+        reference.follow(Some((4, 0)));
+        assert_eq!(reference.error, None);
+        reference.follow(Some((5, 0)));
+        assert!(reference.error.is_some());
+    }
+}
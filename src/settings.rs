@@ -0,0 +1,610 @@
+use bevy::prelude::*;
+use bevy::window::{CompositeAlphaMode, WindowLevel};
+use egui::Color32;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+// constants.rs
+use crate::constants::*;
+// resources.rs
+use crate::resources::*;
+// relay.rs
+use crate::relay::Relay;
+// gradient.rs
+use crate::gradient::{Compound, Thresholds};
+// plot.rs
+use crate::plot::XAxis;
+// cli.rs
+use crate::cli::Options;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PressureUnit {
+    Bar,
+    Psi,
+}
+impl PressureUnit {
+    pub const ALL: [PressureUnit; 2] = [PressureUnit::Bar, PressureUnit::Psi];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PressureUnit::Bar => "bar",
+            PressureUnit::Psi => "psi",
+        }
+    }
+
+    pub fn convert(&self, pressure: f32) -> f32 {
+        match self {
+            PressureUnit::Bar => pressure / PASCAL_PER_BAR,
+            PressureUnit::Psi => pressure / PASCAL_PER_PSI,
+        }
+    }
+
+    pub fn format(&self, pressure: f32) -> String {
+        match self {
+            PressureUnit::Bar => format!("{:.2}", self.convert(pressure)),
+            PressureUnit::Psi => format!("{:.1}", self.convert(pressure)),
+        }
+    }
+
+    pub fn format_delta(&self, pressure: f32) -> String {
+        match self {
+            PressureUnit::Bar => format!("{:+.2}", self.convert(pressure)),
+            PressureUnit::Psi => format!("{:+.1}", self.convert(pressure)),
+        }
+    }
+}
+
+// Redline in rpm, per car index when set, and where the shift light starts as a
+// share of the redline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineSettings {
+    pub redline: f32,
+    pub cars: HashMap<String, f32>,
+    pub shift_light: f32,
+    pub overheat_temperature: f32, // °C, coolant
+}
+impl EngineSettings {
+    pub fn redline(&self, car: i32) -> f32 {
+        self.cars.get(&car.to_string()).copied().unwrap_or(self.redline)
+    }
+
+    fn validated(mut self) -> EngineSettings {
+        let default = EngineSettings::default();
+        let valid = |rpm: &f32| (1000.0..=20000.0).contains(rpm);
+        if !valid(&self.redline) {
+            self.redline = default.redline;
+        }
+        self.cars.retain(|_, rpm| valid(rpm));
+        if !(0.5..1.0).contains(&self.shift_light) {
+            self.shift_light = default.shift_light;
+        }
+        if !self.overheat_temperature.is_finite() {
+            self.overheat_temperature = default.overheat_temperature;
+        }
+        self
+    }
+}
+impl Default for EngineSettings {
+    fn default() -> Self {
+        EngineSettings {
+            redline: REDLINE,
+            cars: HashMap::new(),
+            shift_light: SHIFT_LIGHT_START,
+            overheat_temperature: ENGINE_OVERHEAT_TEMP,
+        }
+    }
+}
+
+// A disk above `temperature` °C for longer than `time` seconds is flagged as fading.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrakeFadeSettings {
+    pub temperature: f32,
+    pub time: f32,
+}
+impl BrakeFadeSettings {
+    fn validated(self) -> BrakeFadeSettings {
+        let valid = (0.0..=2000.0).contains(&self.temperature) && (0.0..=600.0).contains(&self.time);
+        if !valid {
+            return BrakeFadeSettings::default();
+        }
+        self
+    }
+}
+impl Default for BrakeFadeSettings {
+    fn default() -> Self {
+        BrakeFadeSettings {
+            temperature: BRAKE_FADE_TEMP,
+            time: BRAKE_FADE_TIME,
+        }
+    }
+}
+
+// Spring deflection range in meters, the ends of the bars in the suspension view.
+// Travel differs a lot between cars, set it per car index for bottoming out to mean anything.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SuspensionSettings {
+    pub travel: TravelRange,
+    pub cars: HashMap<String, TravelRange>,
+}
+impl SuspensionSettings {
+    pub fn travel(&self, car: i32) -> TravelRange {
+        self.cars.get(&car.to_string()).copied().unwrap_or(self.travel)
+    }
+
+    fn validated(mut self) -> SuspensionSettings {
+        if !self.travel.is_valid() {
+            self.travel = SuspensionSettings::default().travel;
+        }
+        self.cars.retain(|_, travel| travel.is_valid());
+        self
+    }
+}
+impl Default for SuspensionSettings {
+    fn default() -> Self {
+        SuspensionSettings {
+            travel: TravelRange {
+                min: MIN_SUSPENSION_TRAVEL,
+                max: MAX_SUSPENSION_TRAVEL,
+            },
+            cars: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TravelRange {
+    pub min: f32,
+    pub max: f32,
+}
+impl TravelRange {
+    fn is_valid(&self) -> bool {
+        self.min.is_finite() && self.max.is_finite() && self.min < self.max
+    }
+
+    pub fn fraction(&self, deflection: f32) -> f32 {
+        ((deflection - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+    }
+
+    pub fn is_at_limit(&self, deflection: f32) -> bool {
+        deflection <= self.min + BOTTOM_OUT_MARGIN || deflection >= self.max - BOTTOM_OUT_MARGIN
+    }
+}
+
+// Knee speeds between low and high speed damping in m/s, and whether the
+// histogram covers the whole stage or the last `window` seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DamperSettings {
+    pub bump_knee: f32,
+    pub rebound_knee: f32,
+    pub rolling: bool,
+    pub window: f32,
+}
+impl DamperSettings {
+    fn validated(mut self) -> DamperSettings {
+        let default = DamperSettings::default();
+        if !(0.001..=1.0).contains(&self.bump_knee) {
+            self.bump_knee = default.bump_knee;
+        }
+        if !(0.001..=1.0).contains(&self.rebound_knee) {
+            self.rebound_knee = default.rebound_knee;
+        }
+        if !(1.0..=600.0).contains(&self.window) {
+            self.window = default.window;
+        }
+        self
+    }
+}
+impl Default for DamperSettings {
+    fn default() -> Self {
+        DamperSettings {
+            bump_knee: DAMPER_BUMP_KNEE,
+            rebound_knee: DAMPER_REBOUND_KNEE,
+            rolling: false,
+            window: DAMPER_WINDOW,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphToggles {
+    pub throttle: bool,
+    pub brake: bool,
+    pub handbrake: bool,
+    pub clutch: bool,
+    pub gear: bool,
+}
+impl Default for GraphToggles {
+    fn default() -> Self {
+        let checkboxes = PedalCheckboxes::default();
+        GraphToggles {
+            throttle: checkboxes.throttle,
+            brake: checkboxes.brake,
+            handbrake: checkboxes.handbrake,
+            clutch: checkboxes.clutch,
+            gear: checkboxes.gear,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub x: i32,
+    pub y: i32,
+    pub scale: f32,
+    pub opacity: f32,
+    pub always_on_top: bool,
+}
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            x: WINDOW_POSITION.x,
+            y: WINDOW_POSITION.y,
+            scale: 1.0,
+            opacity: 1.0,
+            always_on_top: true,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub ip: String,
+    pub port: String,
+    pub view: DisplayState,
+    pub link_timeout: f32,
+    pub relay_enabled: bool,
+    pub relay_targets: String,
+    pub graphs: GraphToggles,
+    // seconds of every channel kept for the graphs
+    pub history_duration: f32,
+    pub graph_axis: XAxis,
+    pub compound: Compound,
+    pub pressure_unit: PressureUnit,
+    pub thresholds: Thresholds,
+    pub brake_fade: BrakeFadeSettings,
+    pub engine: EngineSettings,
+    pub suspension: SuspensionSettings,
+    pub dampers: DamperSettings,
+    pub window: WindowSettings,
+    // why the file couldn't be used, shown in the main menu
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+// Only what goes into the file, so a new error alone isn't a change to save.
+impl PartialEq for Settings {
+    fn eq(&self, other: &Self) -> bool {
+        let Settings {
+            ip,
+            port,
+            view,
+            link_timeout,
+            relay_enabled,
+            relay_targets,
+            graphs,
+            history_duration,
+            graph_axis,
+            compound,
+            pressure_unit,
+            thresholds,
+            brake_fade,
+            engine,
+            suspension,
+            dampers,
+            window,
+            error: _,
+        } = self;
+        *ip == other.ip
+            && *port == other.port
+            && *view == other.view
+            && *link_timeout == other.link_timeout
+            && *relay_enabled == other.relay_enabled
+            && *relay_targets == other.relay_targets
+            && *graphs == other.graphs
+            && *history_duration == other.history_duration
+            && *graph_axis == other.graph_axis
+            && *compound == other.compound
+            && *pressure_unit == other.pressure_unit
+            && *thresholds == other.thresholds
+            && *brake_fade == other.brake_fade
+            && *engine == other.engine
+            && *suspension == other.suspension
+            && *dampers == other.dampers
+            && *window == other.window
+    }
+}
+
+impl Settings {
+    // A missing file gives the defaults, a corrupt one is kept next to it as .bak.
+    pub fn load() -> Settings {
+        Settings::load_from(Path::new(SETTINGS_PATH))
+    }
+
+    pub fn load_from(path: &Path) -> Settings {
+        let Ok(text) = fs::read_to_string(path) else {
+            return Settings::default();
+        };
+        match toml::from_str::<Settings>(&text) {
+            Ok(settings) => settings.validated(),
+            Err(e) => {
+                let _ = fs::rename(path, path.with_extension("toml.bak"));
+                Settings {
+                    error: Some(format!("Settings reset, {} was invalid: {}", path.display(), e.message())),
+                    ..default()
+                }
+            },
+        }
+    }
+
+    pub fn save(&mut self) {
+        let result = toml::to_string_pretty(self)
+            .map_err(|e| e.to_string())
+            .and_then(|text| fs::write(SETTINGS_PATH, text).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            self.error = Some(format!("Couldn't save settings: {e}"));
+        }
+    }
+
+    // Values that parse but make no sense fall back to their defaults one by one.
+    pub fn validated(mut self) -> Settings {
+        let default = Settings::default();
+        if self.ip.trim().parse::<IpAddr>().is_err() {
+            self.ip = default.ip;
+        }
+        if !self.port.is_empty() && self.port.trim().parse::<u16>().is_err() {
+            self.port = default.port;
+        }
+        if !(1.0..=60.0).contains(&self.link_timeout) {
+            self.link_timeout = default.link_timeout;
+        }
+        if !(1.0..=MAX_HISTORY_DURATION).contains(&self.history_duration) {
+            self.history_duration = default.history_duration;
+        }
+        self.thresholds = self.thresholds.validated();
+        self.brake_fade = self.brake_fade.validated();
+        self.dampers = self.dampers.validated();
+        self.engine = self.engine.validated();
+        self.suspension = self.suspension.validated();
+        let window = &mut self.window;
+        if !(-10000..=10000).contains(&window.x) || !(-10000..=10000).contains(&window.y) {
+            window.x = default.window.x;
+            window.y = default.window.y;
+        }
+        if !(MIN_WINDOW_SCALE..=MAX_WINDOW_SCALE).contains(&window.scale) {
+            window.scale = default.window.scale;
+        }
+        if !(MIN_OPACITY..=1.0).contains(&window.opacity) {
+            window.opacity = default.window.opacity;
+        }
+        self
+    }
+
+    pub fn background(&self) -> Color32 {
+        let [r, g, b, _] = MENU_BG.to_array();
+        Color32::from_rgba_unmultiplied(r, g, b, (self.window.opacity * 255.0) as u8)
+    }
+
+    pub fn window(&self) -> Window {
+        let mut window = Window {
+            resizable: false,
+            position: WindowPosition::At(IVec2::new(self.window.x, self.window.y)),
+            window_level: if self.window.always_on_top {
+                WindowLevel::AlwaysOnTop
+            } else {
+                WindowLevel::Normal
+            },
+            // the clear color is already transparent, this lets the menu background show through
+            transparent: self.window.opacity < 1.0,
+            composite_alpha_mode: CompositeAlphaMode::Auto,
+            ..default()
+        };
+        window.resolution.set_scale_factor_override(Some(self.window.scale));
+        window
+    }
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            ip: UDP_IP.to_string(),
+            port: String::new(),
+            view: DisplayState::Main,
+            link_timeout: LINK_TIMEOUT,
+            relay_enabled: false,
+            relay_targets: String::new(),
+            graphs: GraphToggles::default(),
+            history_duration: HISTORY_DURATION,
+            graph_axis: XAxis::Time,
+            compound: Compound::Gravel,
+            pressure_unit: PressureUnit::Bar,
+            thresholds: Thresholds::default(),
+            brake_fade: BrakeFadeSettings::default(),
+            engine: EngineSettings::default(),
+            suspension: SuspensionSettings::default(),
+            dampers: DamperSettings::default(),
+            window: WindowSettings::default(),
+            error: None,
+        }
+    }
+}
+
+// The settings as last written to the file, without the launch options.
+#[derive(Resource, Deref, DerefMut)]
+pub struct SavedSettings(pub Settings);
+
+// Copies the loaded settings into the resources the menus edit.
+pub fn apply_settings(
+    settings: Res<Settings>,
+    mut port: ResMut<Port>,
+    mut checkboxes: ResMut<PedalCheckboxes>,
+    mut link: ResMut<LinkHealth>,
+    mut relay: ResMut<Relay>,
+    mut dampers: ResMut<Dampers>,
+    mut history: ResMut<History>,
+    mut next_state: ResMut<NextState<DisplayState>>,
+) {
+    port.ip = settings.ip.clone();
+    port.port = settings.port.clone();
+    checkboxes.throttle = settings.graphs.throttle;
+    checkboxes.brake = settings.graphs.brake;
+    checkboxes.handbrake = settings.graphs.handbrake;
+    checkboxes.clutch = settings.graphs.clutch;
+    checkboxes.gear = settings.graphs.gear;
+    link.timeout = settings.link_timeout;
+    relay.enabled = settings.relay_enabled;
+    relay.targets = settings.relay_targets.clone();
+    relay.apply(&port.ip, &port.port);
+    history.set_duration(settings.history_duration);
+    dampers.histogram.set_window(settings.dampers.rolling.then_some(settings.dampers.window));
+    next_state.set(settings.view.clone());
+}
+
+// Writes the file when anything the user can change differs from what was saved.
+pub fn save_settings(
+    mut saved: ResMut<SavedSettings>,
+    options: Res<Options>,
+    mut settings: ResMut<Settings>,
+    windows: Query<&Window>,
+    port: Res<Port>,
+    checkboxes: Res<PedalCheckboxes>,
+    link: Res<LinkHealth>,
+    relay: Res<Relay>,
+    view: Res<State<DisplayState>>,
+) {
+    let current = &mut *settings;
+    current.ip = port.ip.clone();
+    current.port = port.port.clone();
+    current.view = view.get().clone();
+    current.link_timeout = link.timeout;
+    current.relay_enabled = relay.enabled;
+    current.relay_targets = relay.targets.clone();
+    current.graphs = GraphToggles {
+        throttle: checkboxes.throttle,
+        brake: checkboxes.brake,
+        handbrake: checkboxes.handbrake,
+        clutch: checkboxes.clutch,
+        gear: checkboxes.gear,
+    };
+    if let Ok(window) = windows.get_single() {
+        if let WindowPosition::At(position) = window.position {
+            current.window.x = position.x;
+            current.window.y = position.y;
+        }
+    }
+    let mut persisted = options.persisted(current, &saved);
+    if persisted != **saved {
+        persisted.save();
+        current.error = persisted.error.clone();
+        **saved = persisted;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rbr-settings-{name}-{}.toml", std::process::id()))
+    }
+
+    #[test]
+    fn pressures_convert_from_pascal() {
+        assert_eq!(PressureUnit::Bar.convert(200_000.0), 2.0);
+        assert!((PressureUnit::Psi.convert(200_000.0) - 29.0076).abs() < 1e-3);
+        assert_eq!(PressureUnit::Bar.format(185_000.0), "1.85");
+        assert_eq!(PressureUnit::Psi.format(185_000.0), "26.8");
+        assert_eq!(PressureUnit::Bar.format_delta(12_000.0), "+0.12");
+        assert_eq!(PressureUnit::Psi.format_delta(-12_000.0), "-1.7");
+    }
+
+    #[test]
+    fn invalid_fields_fall_back_one_by_one() {
+        let mut settings = Settings {
+            ip: "not an address".to_string(),
+            port: "70000".to_string(),
+            link_timeout: 0.0,
+            history_duration: f32::NAN,
+            pressure_unit: PressureUnit::Psi,
+            ..Default::default()
+        };
+        settings.window.scale = 100.0;
+        settings.window.opacity = 0.8;
+        settings.dampers.window = 0.0;
+        settings.engine.redline = 50.0;
+        settings.brake_fade.temperature = -100.0;
+        settings.engine.cars.insert("3".to_string(), 9000.0);
+        settings.suspension.travel = TravelRange { min: 0.3, max: 0.1 };
+        settings.suspension.cars.insert("3".to_string(), TravelRange { min: 0.1, max: 0.3 });
+        settings.suspension.cars.insert("4".to_string(), TravelRange { min: 0.1, max: f32::NAN });
+        let settings = settings.validated();
+        let default = Settings::default();
+        assert_eq!(settings.ip, default.ip);
+        assert_eq!(settings.port, default.port);
+        assert_eq!(settings.link_timeout, default.link_timeout);
+        assert_eq!(settings.history_duration, default.history_duration);
+        assert_eq!(settings.suspension.travel, default.suspension.travel);
+        assert_eq!(settings.suspension.travel(4), default.suspension.travel);
+        assert_eq!(settings.window.scale, default.window.scale);
+        assert_eq!(settings.dampers.window, default.dampers.window);
+        assert_eq!(settings.engine.redline, default.engine.redline);
+        assert_eq!(settings.brake_fade, default.brake_fade);
+        // the valid ones are kept
+        assert_eq!(settings.pressure_unit, PressureUnit::Psi);
+        assert_eq!(settings.window.opacity, 0.8);
+        assert_eq!(settings.engine.redline(3), 9000.0);
+        assert_eq!(settings.suspension.travel(3), TravelRange { min: 0.1, max: 0.3 });
+    }
+
+    #[test]
+    fn valid_file_round_trips() {
+        let path = temp_path("valid");
+        let mut settings = Settings {
+            port: "6777".to_string(),
+            view: DisplayState::Pedals,
+            ..Default::default()
+        };
+        fs::write(&path, toml::to_string_pretty(&settings).unwrap()).unwrap();
+        let loaded = Settings::load_from(&path);
+        fs::remove_file(&path).unwrap();
+        settings.error = None;
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn unparsable_file_falls_back_to_defaults() {
+        let path = temp_path("corrupt");
+        fs::write(&path, "port = [\n").unwrap();
+        let settings = Settings::load_from(&path);
+        let backup = path.with_extension("toml.bak");
+        assert!(!path.exists());
+        assert!(backup.exists());
+        fs::remove_file(&backup).unwrap();
+        assert!(settings.error.is_some());
+        assert_eq!(Settings { error: None, ..settings }, Settings::default());
+    }
+
+    #[test]
+    fn missing_file_gives_defaults() {
+        let settings = Settings::load_from(&temp_path("missing"));
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn an_error_alone_is_not_a_change_to_save() {
+        let saved = Settings::default();
+        let mut current = saved.clone();
+        current.error = Some("Couldn't save settings".to_string());
+        assert_eq!(current, saved);
+        current.port = "6777".to_string();
+        assert_ne!(current, saved);
+    }
+}
+
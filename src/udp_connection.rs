@@ -1,16 +1,30 @@
 use bevy::prelude::*;
+use std::io::ErrorKind;
+use std::time::Instant;
 
 // resources.rs
 use crate::resources::*;
+use rbr_telemetry::MAX_PACKET_SIZE;
+// constants.rs
+use crate::constants::MAX_PACKETS_PER_FRAME;
+// recorder.rs
+use crate::recorder::Recorder;
+// relay.rs
+use crate::relay::Relay;
+// settings.rs
+use crate::settings::Settings;
 
 
 pub fn connect_udp(
     mut socket: ResMut<Socket>,
     mut next_state: ResMut<NextState<ConnectionState>>,
+    mut link: ResMut<LinkHealth>,
+    mut relay: ResMut<Relay>,
     port: Res<Port>
 ) {
-    let p = &port.port;
-    socket.bind(p);
+    socket.bind(&port.ip, &port.port);
+    link.reset();
+    relay.apply(&port.ip, &port.port);
     match socket.socket {
         Ok(_) => {
             next_state.set(ConnectionState::Connected);
@@ -23,30 +37,168 @@ pub fn connect_udp(
 
 pub fn telemetry_handler(
     mut rbr: ResMut<RBR>,
+    mut recorder: ResMut<Recorder>,
+    mut history: ResMut<History>,
+    mut inputs: ResMut<InputHistory>,
+    mut dampers: ResMut<Dampers>,
+    mut pressures: ResMut<Pressures>,
+    mut brakes: ResMut<Brakes>,
+    mut engine: ResMut<EngineHistory>,
+    mut bests: ResMut<Bests>,
+    settings: Res<Settings>,
+    mut link: ResMut<LinkHealth>,
+    mut relay: ResMut<Relay>,
     socket: Res<Socket>,
     mut next_state: ResMut<NextState<ConnectionState>>,
 ) {
-    
-    let mut buf = [0; 664];
+    relay.poll();
+    // one spare byte so oversized packets show up as the wrong size instead of being truncated
+    let mut buf = [0; MAX_PACKET_SIZE + 1];
     let socket = &socket.socket.as_ref();
     match socket.ok() {
         Some(udp_socket) => {
-            udp_socket.set_nonblocking(true)
-                .expect("Failed to enter non-blocking mode");
-            match udp_socket.recv(&mut buf).ok() {
-                Some(_) => {
-                    rbr.recv = true;
-                    rbr.get_data(&buf);
-                },
-                None => {
-                    rbr.recv = false;
+            // read everything that queued up since the last frame, the newest packet is displayed
+            let mut displayed = 0;
+            for _ in 0..MAX_PACKETS_PER_FRAME {
+                let len = match udp_socket.recv(&mut buf) {
+                    Ok(len) => len,
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        rbr.stats.socket_error(&e);
+                        break;
+                    },
+                };
+                let received = Instant::now();
+                rbr.stats.add(received);
+                let data = &buf[..len];
+                // forwarded as received, including packets we can't decode
+                relay.forward(data);
+                let Ok(telemetry) = rbr.decode(data) else {
+                    continue;
+                };
+                link.packet(&telemetry, received);
+                if let Some(layout) = rbr.protocol {
+                    recorder.record(data, &telemetry, layout.version, received);
                 }
+                if rbr.is_stale(&telemetry) {
+                    rbr.stats.stale += 1;
+                    continue;
+                }
+                history.add(&telemetry);
+                inputs.add_received(received, &telemetry);
+                dampers.histogram.add_car(telemetry.stage.race_time, &telemetry.car);
+                pressures.add(&telemetry);
+                brakes.add(&telemetry, settings.brake_fade.temperature);
+                engine.add(&telemetry);
+                bests.add(&telemetry);
+                rbr.telemetry = telemetry;
+                displayed += 1;
+            }
+            if displayed > 1 {
+                rbr.stats.skipped += displayed - 1;
             }
-            
-            
+            let now = Instant::now();
+            rbr.stats.update(now);
+            link.update(now);
         },
         None => {
             next_state.set(ConnectionState::Disconnected);
         },
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::UdpSocket;
+    use std::time::Duration;
+    use rbr_telemetry::{Channel, Telemetry, TELEMETRY_DATA_LAYOUT};
+
+    // The live path as main sets it up, listening on an ephemeral port.
+    fn live_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_state(ConnectionState::Disconnected)
+            .init_resource::<Socket>()
+            .init_resource::<RBR>()
+            .insert_resource(Port {
+                ip: "127.0.0.1".to_string(),
+                port: "0".to_string(),
+            })
+            .init_resource::<LinkHealth>()
+            .init_resource::<History>()
+            .init_resource::<InputHistory>()
+            .init_resource::<Dampers>()
+            .init_resource::<Pressures>()
+            .init_resource::<Brakes>()
+            .init_resource::<EngineHistory>()
+            .init_resource::<Recorder>()
+            .init_resource::<Bests>()
+            .init_resource::<Relay>()
+            .init_resource::<Settings>()
+            .add_systems(
+                Update,
+                (
+                    telemetry_handler.run_if(in_state(ConnectionState::Connected)),
+                    connect_udp.run_if(in_state(ConnectionState::Disconnected)),
+                ),
+            );
+        app
+    }
+
+    fn frame(step: u32) -> Vec<u8> {
+        let mut telemetry = Telemetry {
+            total_steps: step,
+            ..Default::default()
+        };
+        telemetry.stage.index = 3;
+        telemetry.car.index = 7;
+        telemetry.stage.race_time = 1.0 + step as f32 * 0.01;
+        telemetry.stage.distance_to_end = 1000.0;
+        telemetry.control.throttle = 0.25;
+        telemetry.control.gear = 4;
+        TELEMETRY_DATA_LAYOUT.encode(&telemetry)
+    }
+
+    // Updates until `done` holds, the packets take a moment to arrive even on loopback.
+    fn update_until(app: &mut App, done: impl Fn(&World) -> bool) {
+        for _ in 0..200 {
+            app.update();
+            if done(&app.world) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("timed out");
+    }
+
+    #[test]
+    fn received_packets_reach_the_rbr_resource() {
+        let mut app = live_app();
+        update_until(&mut app, |world| *world.resource::<State<ConnectionState>>().get() == ConnectionState::Connected);
+        let address = app.world.resource::<Socket>().socket.as_ref().unwrap().local_addr().unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for step in 0..5 {
+            sender.send_to(&frame(step), address).unwrap();
+        }
+        // a packet that fails validation doesn't replace the last good frame
+        let mut bad = Telemetry::default();
+        bad.control.throttle = f32::NAN;
+        sender.send_to(&TELEMETRY_DATA_LAYOUT.encode(&bad), address).unwrap();
+        update_until(&mut app, |world| world.resource::<RBR>().stats.received == 6);
+
+        let rbr = app.world.resource::<RBR>();
+        assert_eq!(rbr.telemetry.total_steps, 4);
+        assert_eq!(rbr.telemetry.stage.index, 3);
+        assert_eq!(rbr.telemetry.car.index, 7);
+        assert_eq!(rbr.telemetry.control.throttle, 25.0);
+        assert_eq!(rbr.telemetry.control.gear, 3);
+        assert_eq!(rbr.rejected.non_finite, 1);
+        assert_eq!(rbr.protocol.map(|layout| layout.version), Some(TELEMETRY_DATA_LAYOUT.version));
+        let history = app.world.resource::<History>();
+        assert_eq!(history.len(), 5);
+        assert_eq!(history.latest(Channel::GEAR), Some(3.0));
+        assert_eq!(app.world.resource::<LinkHealth>().status, LinkStatus::Live);
+    }
+}
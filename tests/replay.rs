@@ -0,0 +1,71 @@
+// Runs rbr-replay against an ephemeral port and checks what arrives decodes like the recording.
+
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::UdpSocket;
+use std::process::{self, Command};
+use std::time::{Duration, Instant};
+
+use rbr_telemetry::*;
+
+const FRAMES: u32 = 20;
+const FRAME_INTERVAL_US: u64 = 10_000;
+
+fn write_session(path: &std::path::Path) {
+    let mut writer = BufWriter::new(File::create(path).unwrap());
+    write_header(&mut writer, &SessionHeader::new(3, 7, TELEMETRY_DATA_LAYOUT.version)).unwrap();
+    for i in 0..FRAMES {
+        let mut telemetry = Telemetry {
+            total_steps: i,
+            ..Default::default()
+        };
+        telemetry.stage.index = 3;
+        telemetry.stage.race_time = i as f32 * 0.01;
+        telemetry.control.throttle = 0.5;
+        telemetry.control.gear = 2;
+        telemetry.car.index = 7;
+        let frame = SessionFrame {
+            time_us: i as u64 * FRAME_INTERVAL_US,
+            data: TELEMETRY_DATA_LAYOUT.encode(&telemetry),
+        };
+        write_frame(&mut writer, &frame).unwrap();
+    }
+    writer.flush().unwrap();
+}
+
+#[test]
+fn replays_recorded_session_over_udp() {
+    let path = env::temp_dir().join(format!("rbr-replay-test-{}.rbrs", process::id()));
+    write_session(&path);
+    let session = Session::load(&path).unwrap();
+    assert_eq!(session.header.stage_index, 3);
+    assert_eq!(session.frames.len(), FRAMES as usize);
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let port = socket.local_addr().unwrap().port();
+    let start = Instant::now();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rbr-replay"))
+        .arg(&path)
+        .args(["--port", &port.to_string(), "--speed", "2"])
+        .spawn()
+        .unwrap();
+
+    let mut buf = [0; MAX_PACKET_SIZE + 1];
+    for i in 0..FRAMES {
+        let len = socket.recv(&mut buf).unwrap();
+        let telemetry = Telemetry::decode(&buf[..len]).unwrap();
+        assert_eq!(telemetry.total_steps, i);
+        assert_eq!(telemetry.car.index, 7);
+        assert_eq!(telemetry.control.throttle, 50.0);
+        assert_eq!(telemetry.control.gear, 1);
+    }
+    let elapsed = start.elapsed();
+    assert!(child.wait().unwrap().success());
+    std::fs::remove_file(&path).unwrap();
+
+    // 190ms of recording at 2x speed
+    let recorded = Duration::from_micros((FRAMES as u64 - 1) * FRAME_INTERVAL_US);
+    assert!(elapsed >= recorded / 2, "replayed too fast: {elapsed:?}");
+}